@@ -24,7 +24,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .pos_startpos()
         .go_opt("depth", 12);
 
-    let engine = UciEngine::new("./stockfish12");
+    let engine = UciEngine::try_new("./stockfish12").await?;
 
     // make two clones of the engine, so that we can move them to async blocks
     let (engine_clone1, engine_clone2) = (engine.clone(), engine.clone());
@@ -45,11 +45,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // wait enough for the go commands to complete in the background
     tokio::time::sleep(tokio::time::Duration::from_millis(20000)).await;
 
-    // quit engine
-    engine.quit();
+    // quit engine and wait for it to exit
+    let exit_status = engine.quit().await;
 
-    // wait for engine to quit gracefully
-    tokio::time::sleep(tokio::time::Duration::from_millis(3000)).await;
+    println!("engine exit status {:?}", exit_status);
 
     Ok(())
 }
@@ -0,0 +1,34 @@
+extern crate env_logger;
+
+use tokio_stream::StreamExt;
+
+use uciengine::uciengine::*;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let engine = UciEngine::try_new("stockfish12.exe").await?;
+
+    // analyze starting position until stopped
+    let mut session = engine.start_infinite(GoJob::new().pos_startpos());
+
+    for _ in 0..10 {
+        println!("analysis event {:?}", session.next().await);
+    }
+
+    // switch to another position, the stopped search returns its result
+    let go_result = session
+        .retarget(GoJob::new().pos_startpos().pos_moves("e2e4"))
+        .await;
+
+    println!("go result of start position {:?}", go_result);
+
+    for _ in 0..10 {
+        println!("analysis event {:?}", session.next().await);
+    }
+
+    println!("go result after e2e4 {:?}", session.stop().await);
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+extern crate env_logger;
+
+use uciengine::supervisor::*;
+use uciengine::uciengine::*;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let engine = SupervisedEngine::try_new("stockfish12.exe")
+        .await?
+        .max_retries(5)
+        .backoff(tokio::time::Duration::from_millis(200));
+
+    let mut events = engine.subscribe();
+
+    tokio::spawn(async move {
+        while let Ok(event) = events.recv().await {
+            println!("supervisor event {:?}", event);
+        }
+    });
+
+    let go_job = GoJob::new()
+        .uci_opt("UCI_Variant", "atomic")
+        .pos_startpos()
+        .go_opt("depth", 12);
+
+    // if the engine dies during the search, it is restarted and the job is retried
+    let go_result = engine.go(go_job).await?;
+
+    println!("go result {:?} , restarts {}", go_result, engine.restarts());
+
+    println!("engine exit status {:?}", engine.quit().await);
+
+    Ok(())
+}
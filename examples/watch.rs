@@ -1,5 +1,8 @@
 extern crate env_logger;
 
+use tokio_stream::StreamExt;
+
+use uciengine::analysis::*;
 use uciengine::uciengine::*;
 
 #[tokio::main]
@@ -12,24 +15,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .pos_moves("e2e4 e7e5")
         .go_opt("depth", 24);
 
-    let engine = UciEngine::new("stockfish12.exe");
-
-    // start engine detached
-    let _ = engine.go(go_job);
-
-    let mut arx = engine.atx.subscribe();
+    let engine = UciEngine::try_new("stockfish12.exe").await?;
 
-    loop {
-        let rec_result = arx.recv().await;
+    // stream the infos and info strings of this search only
+    let mut analysis = engine.analyze(go_job);
 
-        println!("rec result {:?}", rec_result);
-
-        if let Ok(rec_result) = rec_result {
-            if rec_result.done {
-                break;
-            }
+    while let Some(event) = analysis.next().await {
+        match event {
+            AnalysisEvent::Info(ai) => println!("analysis info {:?}", ai),
+            AnalysisEvent::String(string) => println!("info string {}", string),
         }
     }
 
+    println!("go result {:?}", analysis.result().await);
+
     Ok(())
 }
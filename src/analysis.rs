@@ -1,11 +1,15 @@
-use log::{error, warn};
+use log::{debug, error, log_enabled, Level};
 
 use envor::envor::env_true;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use thiserror::Error;
 
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ucimove::*;
+
 /// InfoParseError captures possible info parsing errors
 #[derive(Error, Debug)]
 pub enum InfoParseError {
@@ -15,192 +19,136 @@ pub enum InfoParseError {
     InvalidKeyError(String),
     #[error("invalid score specifier '{0}'")]
     InvalidScoreSpecifier(String),
+    #[error("invalid move '{0}'")]
+    InvalidMoveError(String),
 }
 
 /// log info parse error and return it as a result
+#[deprecated(
+    note = "parse errors are logged by AnalysisInfo::parse_with, see InfoParserConfig::log_errors"
+)]
 pub fn info_parse_error(err: InfoParseError) -> Result<(), InfoParseError> {
     error!("{:?}", err);
 
     Err(err)
 }
 
+/// info keys of the uci protocol, an unknown key takes all tokens up to the next one of these
+pub const INFO_KEYWORDS: [&str; 19] = [
+    "depth",
+    "seldepth",
+    "time",
+    "nodes",
+    "pv",
+    "multipv",
+    "score",
+    "lowerbound",
+    "upperbound",
+    "wdl",
+    "currmove",
+    "currmovenumber",
+    "hashfull",
+    "nps",
+    "tbhits",
+    "cpuload",
+    "string",
+    "refutation",
+    "currline",
+];
+
+/// parse move of info
+fn parse_move(token: &str) -> Result<UciMove, InfoParseError> {
+    UciMove::parse(token).map_err(|_| InfoParseError::InvalidMoveError(token.to_string()))
+}
+
+/// return parse number error as a result, without logging it
+fn number_error(ps: ParsingState, value: &str) -> Result<(), InfoParseError> {
+    Err(InfoParseError::ParseNumberError(ps, value.to_string()))
+}
+
 /// log parse number error and return it as a result
+#[deprecated(
+    note = "parse errors are logged by AnalysisInfo::parse_with, see InfoParserConfig::log_errors"
+)]
 pub fn parse_number_error<T: AsRef<str>>(ps: ParsingState, value: T) -> Result<(), InfoParseError> {
-    let value = value.as_ref().to_string();
+    let err = InfoParseError::ParseNumberError(ps, value.as_ref().to_string());
 
-    info_parse_error(InfoParseError::ParseNumberError(ps, value))
+    error!("{:?}", err);
+
+    Err(err)
 }
 
-/// generate string buffer with given name and size
-macro_rules! gen_str_buff {
-	($(#[$attr:meta] => $type:ident, $size:expr),*) => { $(
-	    #[$attr]
-	    #[derive(Clone, Copy)]
-		pub struct $type {
-			pub len: usize,
-			pub buff: [u8; $size],
-		}
-
-		#[$attr]
-		#[doc = "implementation"]
-		impl $type {
-			#[doc = "create new"]
-			#[$attr]
-			pub fn new() -> Self {
-				Self {
-					len: 0,
-					buff: [0; $size]
-				}
-			}
-
-			#[doc = "convert"]
-			#[$attr]
-			#[doc = "to option ( None if empty, Some(contents) otherwise )"]
-			pub fn to_opt(self) -> Option<String> {
-				if self.len == 0 {
-					return None;
-				}
-
-				Some(String::from(self))
-			}
-
-			#[doc = "set"]
-			#[$attr]
-			#[doc = "( value will be trimmed to buffer size )"]
-      #[must_use]
-			pub fn set<T: AsRef<str>>(&mut self, value: T) -> Self {
-				let bytes = value.as_ref().as_bytes();
-
-				let mut len = bytes.len();
-
-				if len > $size{
-					len = $size;
-				}
-
-				self.len = len;
-
-				self.buff[0..len].copy_from_slice(&bytes[0..len]);
-
-				*self
-			}
-
-			#[doc = "reset"]
-			#[$attr]
-			#[doc = "to empty buffer"]
-      #[must_use]
-			pub fn reset(&mut self) -> Self {
-				self.len = 0;
-
-				*self
-			}
-
-      #[must_use]
-			pub fn set_trim<T: AsRef<str>>(&mut self, value: T, trim: char) -> Self {
-				let value_ref = value.as_ref();
-				let value_string = value_ref.to_string();
-				let bytes = value_ref.as_bytes();
-
-				let mut total_len = value_string.len();
-
-			    value_ref.to_string().chars().rev().take_while(|c| {
-			        total_len -= 1;
-			        ( *c != trim ) || ( total_len > $size )
-			    }).collect::<String>().len();
-
-			    self.len = total_len;
-
-			    self.buff[0..total_len].copy_from_slice(&bytes[0..total_len]);
-
-				*self
-			}
-		}
-
-		#[doc = "implement From<&str> for"]
-		#[$attr]
-		impl std::convert::From<&str> for $type {
-        #[must_use]
-			fn from(value: &str) -> Self {
-				let bytes = value.as_bytes();
-
-				let mut len = bytes.len();
-
-				if len > $size{
-					len = $size;
-				}
-
-				let mut buff = $type::new();
-
-                buff.len = len;
-				buff.buff[0..len].copy_from_slice(&bytes[0..len]);
-
-				buff
-			}
-		}
-
-		#[doc = "implement From<String> for"]
-		#[$attr]
-		impl std::convert::From<String> for $type {
-			fn from(value: String) -> Self {
-				Self::from(value.as_str())
-			}
-		}
-
-		#[doc = "implement From<Option<String>> for"]
-		#[$attr]
-		impl std::convert::From<Option<String>> for $type {
-			fn from(value: Option<String>) -> Self {
-				Self::from(value.unwrap_or(String::new()).as_str())
-			}
-		}
-
-		#[doc = "implement From<"]
-		#[$attr]
-		#[doc = "> for String"]
-		impl std::convert::From<$type> for String {
-			fn from(buff: $type) -> String {
-				std::str::from_utf8(&buff.buff[0..buff.len]).unwrap().to_string()
-			}
-		}
-
-		#[doc = "implement Display for"]
-		#[$attr]
-		impl std::fmt::Display for $type {
-			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		        write!(f, "{}", String::from(*self))
-		    }
-		}
-
-		#[doc = "implement Debug for"]
-		#[$attr]
-		impl std::fmt::Debug for $type {
-			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		        write!(f, "[{}[{}]: '{}']", stringify!($type), self.len, String::from(*self))
-		    }
-		}
-	)* }
+/// info parser configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfoParserConfig {
+    /// collect unknown info keys as extras instead of failing the line
+    pub allow_unknown_key: bool,
+    /// maximum number of pv moves kept, None for the full pv
+    pub max_pv_len: Option<usize>,
+    /// log lines that could not be parsed
+    pub log_errors: bool,
 }
 
-/// maximum length of uci move
-const UCI_MAX_LENGTH: usize = 5;
-/// typical length of uci move
-const UCI_TYPICAL_LENGTH: usize = 4;
-/// maximum number of pv moves to store
-#[cfg(not(test))]
-const MAX_PV_MOVES: usize = 10;
-#[cfg(test)]
-const MAX_PV_MOVES: usize = 2;
-/// pv buffer size
-const PV_BUFF_SIZE: usize = MAX_PV_MOVES * (UCI_TYPICAL_LENGTH + 1);
-
-gen_str_buff!(
-/// UciBuff
-=> UciBuff, UCI_MAX_LENGTH,
-/// PvBuff
-=> PvBuff, PV_BUFF_SIZE
-);
+/// info parser config implementation
+impl InfoParserConfig {
+    /// create strict parser config, keeping the full pv and logging errors
+    pub fn new() -> Self {
+        Self {
+            allow_unknown_key: false,
+            max_pv_len: None,
+            log_errors: true,
+        }
+    }
+
+    /// create parser config from environment, ALLOW_UNKNOWN_INFO_KEY=true allows unknown keys
+    pub fn from_env() -> Self {
+        Self {
+            allow_unknown_key: env_true("ALLOW_UNKNOWN_INFO_KEY"),
+            ..Self::new()
+        }
+    }
+
+    /// parser config of the environment, read once per process
+    pub fn env_default() -> Self {
+        static ENV_CONFIG: std::sync::OnceLock<InfoParserConfig> = std::sync::OnceLock::new();
+
+        *ENV_CONFIG.get_or_init(Self::from_env)
+    }
+
+    /// set whether unknown keys are allowed and return self
+    #[must_use]
+    pub fn allow_unknown_key(mut self, allow_unknown_key: bool) -> Self {
+        self.allow_unknown_key = allow_unknown_key;
+
+        self
+    }
+
+    /// set maximum pv length and return self
+    #[must_use]
+    pub fn max_pv_len(mut self, max_pv_len: usize) -> Self {
+        self.max_pv_len = Some(max_pv_len);
+
+        self
+    }
+
+    /// set whether parse errors are logged and return self
+    #[must_use]
+    pub fn log_errors(mut self, log_errors: bool) -> Self {
+        self.log_errors = log_errors;
+
+        self
+    }
+}
+
+/// implement Default for InfoParserConfig
+impl Default for InfoParserConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// score
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Score {
     /// centipawn
     Cp(i32),
@@ -208,8 +156,19 @@ pub enum Score {
     Mate(i32),
 }
 
+/// win / draw / loss statistics in per mille ( sent with UCI_ShowWDL enabled )
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wdl {
+    /// win
+    pub win: u32,
+    /// draw
+    pub draw: u32,
+    /// loss
+    pub loss: u32,
+}
+
 /// score type
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScoreType {
     /// exact
     Exact,
@@ -287,16 +246,16 @@ pub enum ScoreType {
 // 		The engine should only send this if the option "UCI_ShowCurrLine" is set to true.
 
 /// analysis info
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AnalysisInfo {
     /// false for ongoing analysis, true when analysis stopped on bestmove received
     pub done: bool,
     /// best move
-    bestmove: UciBuff,
+    bestmove: Option<UciMove>,
     /// ponder
-    ponder: UciBuff,
-    /// pv
-    pv: PvBuff,
+    ponder: Option<UciMove>,
+    /// pv ( full length, not trimmed )
+    pv: Vec<UciMove>,
     /// depth
     pub depth: usize,
     /// seldepth
@@ -310,7 +269,7 @@ pub struct AnalysisInfo {
     /// score ( centipawns or mate )
     pub score: Score,
     /// current move
-    pub currmove: UciBuff,
+    pub currmove: Option<UciMove>,
     /// current move number
     pub currmovenumber: usize,
     /// hashfull
@@ -323,6 +282,16 @@ pub struct AnalysisInfo {
     pub cpuload: usize,
     /// score type
     pub scoretype: ScoreType,
+    /// win / draw / loss of the score, if reported
+    pub wdl: Option<Wdl>,
+    /// values of unknown info keys by key, space separated, empty for a key without value
+    pub extras: HashMap<String, String>,
+    /// text of the last line if it was an info string, None otherwise
+    pub string: Option<String>,
+    /// refutation lines by refuted move
+    refutations: HashMap<UciMove, Vec<UciMove>>,
+    /// current lines by cpu number ( starting from 1 )
+    currlines: BTreeMap<usize, Vec<UciMove>>,
 }
 
 /// analysis info serde
@@ -333,11 +302,12 @@ pub struct AnalysisInfoSerde {
     /// false for ongoing analysis, true when analysis stopped on bestmove received
     pub done: bool,
     /// best move
-    pub bestmove: Option<String>,
+    pub bestmove: Option<UciMove>,
     /// ponder
-    pub ponder: Option<String>,
-    /// pv
-    pub pv: Option<String>,
+    pub ponder: Option<UciMove>,
+    /// pv ( serialized as space separated string )
+    #[serde(with = "pv_string")]
+    pub pv: Vec<UciMove>,
     /// depth
     pub depth: usize,
     /// seldepth
@@ -351,7 +321,7 @@ pub struct AnalysisInfoSerde {
     /// score ( centipawns or mate )
     pub score: Score,
     /// current move
-    pub currmove: Option<String>,
+    pub currmove: Option<UciMove>,
     /// current move number
     pub currmovenumber: usize,
     /// hashfull
@@ -364,6 +334,102 @@ pub struct AnalysisInfoSerde {
     pub cpuload: usize,
     /// score type
     pub scoretype: ScoreType,
+    /// win / draw / loss of the score, if reported
+    #[serde(default)]
+    pub wdl: Option<Wdl>,
+    /// values of unknown info keys by key
+    #[serde(default)]
+    pub extras: HashMap<String, String>,
+}
+
+/// serde of pv as optional space separated string
+mod pv_string {
+    use super::*;
+
+    /// serialize pv
+    pub fn serialize<S: Serializer>(pv: &[UciMove], serializer: S) -> Result<S::Ok, S::Error> {
+        if pv.is_empty() {
+            return serializer.serialize_none();
+        }
+
+        serializer.serialize_some(&moves_to_string(pv))
+    }
+
+    /// deserialize pv
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<UciMove>, D::Error> {
+        let pv = Option::<String>::deserialize(deserializer)?;
+
+        UciMove::parse_list(pv.unwrap_or_default()).map_err(serde::de::Error::custom)
+    }
+}
+
+/// analysis lines of a multipv search, one line per multipv index,
+/// lines of a previous depth are dropped when a deeper line arrives
+#[derive(Debug, Clone, Default)]
+pub struct MultiPvSnapshot {
+    /// depth of the deepest line
+    pub depth: usize,
+    /// lines by multipv index
+    lines: BTreeMap<usize, AnalysisInfo>,
+}
+
+/// multipv snapshot implementation
+impl MultiPvSnapshot {
+    /// create new empty snapshot
+    pub fn new() -> Self {
+        Self {
+            depth: 0,
+            lines: BTreeMap::new(),
+        }
+    }
+
+    /// update snapshot with analysis info, infos without pv are ignored,
+    /// infos without multipv are taken as multipv 1
+    pub fn update(&mut self, ai: &AnalysisInfo) {
+        if ai.pv().is_none() {
+            return;
+        }
+
+        if ai.depth > self.depth {
+            self.lines.clear();
+
+            self.depth = ai.depth;
+        }
+
+        self.lines.insert(ai.multipv.max(1), ai.clone());
+    }
+
+    /// get line of multipv index ( starting from 1 )
+    pub fn line(&self, multipv: usize) -> Option<&AnalysisInfo> {
+        self.lines.get(&multipv)
+    }
+
+    /// get lines ordered by multipv index
+    pub fn lines(&self) -> Vec<AnalysisInfo> {
+        self.lines.values().cloned().collect()
+    }
+
+    /// number of lines
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// true if there are no lines
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// event of an analysis stream
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum AnalysisEvent {
+    /// analysis info, updated by an info line
+    Info(AnalysisInfo),
+    /// text of an info string line
+    String(String),
 }
 
 /// parsing state
@@ -382,6 +448,9 @@ pub enum ParsingState {
     Score,
     ScoreCp,
     ScoreMate,
+    WdlWin,
+    WdlDraw,
+    WdlLoss,
     Currmove,
     Currmovenumber,
     Hashfull,
@@ -391,6 +460,10 @@ pub enum ParsingState {
     PvBestmove,
     PvPonder,
     PvRest,
+    Refutation,
+    RefutationLine,
+    Currline,
+    CurrlineMoves,
 }
 
 /// analysis info implementation
@@ -399,33 +472,38 @@ impl AnalysisInfo {
     pub fn new() -> Self {
         Self {
             done: false,
-            bestmove: UciBuff::new(),
-            ponder: UciBuff::new(),
-            pv: PvBuff::new(),
+            bestmove: None,
+            ponder: None,
+            pv: vec![],
             depth: 0,
             seldepth: 0,
             time: 0,
             nodes: 0,
             multipv: 0,
             score: Score::Cp(0),
-            currmove: UciBuff::new(),
+            currmove: None,
             currmovenumber: 0,
             hashfull: 0,
             nps: 0,
             tbhits: 0,
             cpuload: 0,
             scoretype: ScoreType::Exact,
+            wdl: None,
+            extras: HashMap::new(),
+            string: None,
+            refutations: HashMap::new(),
+            currlines: BTreeMap::new(),
         }
     }
 
     /// to serde
-    pub fn to_serde(self) -> AnalysisInfoSerde {
+    pub fn to_serde(&self) -> AnalysisInfoSerde {
         AnalysisInfoSerde {
             disposition: "AnalysisInfo".to_string(),
             done: self.done,
             bestmove: self.bestmove(),
             ponder: self.ponder(),
-            pv: self.pv(),
+            pv: self.pv.clone(),
             depth: self.depth,
             seldepth: self.seldepth,
             time: self.time,
@@ -439,6 +517,8 @@ impl AnalysisInfo {
             tbhits: self.tbhits,
             cpuload: self.cpuload,
             scoretype: self.scoretype,
+            wdl: self.wdl,
+            extras: self.extras.clone(),
         }
     }
 
@@ -446,22 +526,27 @@ impl AnalysisInfo {
     pub fn from_serde(ais: AnalysisInfoSerde) -> Self {
         Self {
             done: ais.done,
-            bestmove: UciBuff::from(ais.bestmove),
-            ponder: UciBuff::from(ais.ponder),
-            pv: PvBuff::from(ais.pv),
+            bestmove: ais.bestmove,
+            ponder: ais.ponder,
+            pv: ais.pv,
             depth: ais.depth,
             seldepth: ais.seldepth,
             time: ais.time,
             nodes: ais.nodes,
             multipv: ais.multipv,
             score: ais.score,
-            currmove: UciBuff::from(ais.currmove),
+            currmove: ais.currmove,
             currmovenumber: ais.currmovenumber,
             hashfull: ais.hashfull,
             nps: ais.nps,
             tbhits: ais.tbhits,
             cpuload: ais.cpuload,
             scoretype: ais.scoretype,
+            wdl: ais.wdl,
+            extras: ais.extras,
+            string: None,
+            refutations: HashMap::new(),
+            currlines: BTreeMap::new(),
         }
     }
 
@@ -474,44 +559,159 @@ impl AnalysisInfo {
     }
 
     /// to json
-    pub fn to_json(self) -> Result<String, serde_json::Error> {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(&self.to_serde())
     }
 
     // get bestmove
-    pub fn bestmove(self) -> Option<String> {
-        self.bestmove.to_opt()
+    pub fn bestmove(&self) -> Option<UciMove> {
+        self.bestmove
     }
 
     // get ponder
-    pub fn ponder(self) -> Option<String> {
-        self.ponder.to_opt()
+    pub fn ponder(&self) -> Option<UciMove> {
+        self.ponder
     }
 
-    // get pv
-    pub fn pv(self) -> Option<String> {
-        self.pv.to_opt()
+    // get pv as space separated string
+    pub fn pv(&self) -> Option<String> {
+        if self.pv.is_empty() {
+            return None;
+        }
+
+        Some(moves_to_string(&self.pv))
+    }
+
+    // get pv as list of moves
+    pub fn pv_moves(&self) -> &[UciMove] {
+        &self.pv
     }
 
     // get current move
-    pub fn currmove(self) -> Option<String> {
-        self.currmove.to_opt()
+    pub fn currmove(&self) -> Option<UciMove> {
+        self.currmove
+    }
+
+    /// get refutation line of move, empty if the engine found no refutation
+    pub fn refutation(&self, uci_move: UciMove) -> Option<&[UciMove]> {
+        self.refutations.get(&uci_move).map(|line| line.as_slice())
     }
 
-    /// parse info string
+    /// get refutation lines by refuted move
+    pub fn refutations(&self) -> &HashMap<UciMove, Vec<UciMove>> {
+        &self.refutations
+    }
+
+    /// get current line of cpu ( starting from 1 )
+    pub fn currline(&self, cpu: usize) -> Option<&[UciMove]> {
+        self.currlines.get(&cpu).map(|line| line.as_slice())
+    }
+
+    /// get current lines by cpu number
+    pub fn currlines(&self) -> &BTreeMap<usize, Vec<UciMove>> {
+        &self.currlines
+    }
+
+    /// add values of unknown key to extras
+    fn add_extra(&mut self, key: Option<&str>, values: &mut Vec<&str>) {
+        if let Some(key) = key {
+            let value = values.join(" ");
+
+            if log_enabled!(Level::Debug) {
+                debug!("unknown info key {} with value '{}'", key, value);
+            }
+
+            self.extras.insert(key.to_string(), value);
+        }
+
+        values.clear();
+    }
+
+    /// parse info line with the parser config of the environment ( see InfoParserConfig::env_default ),
+    /// strict unless ALLOW_UNKNOWN_INFO_KEY=true, use parse_with for another config
     pub fn parse<T: std::convert::AsRef<str>>(&mut self, info: T) -> Result<(), InfoParseError> {
+        self.parse_with(info, &InfoParserConfig::env_default())
+    }
+
+    /// parse info line with parser config
+    pub fn parse_with<T: std::convert::AsRef<str>>(
+        &mut self,
+        info: T,
+        config: &InfoParserConfig,
+    ) -> Result<(), InfoParseError> {
         let info = info.as_ref();
+
+        let parse_result = self.parse_tokens(info, config);
+
+        if let Err(err) = &parse_result {
+            if config.log_errors && log_enabled!(Level::Error) {
+                error!("{} in info '{}'", err, info);
+            }
+        }
+
+        parse_result
+    }
+
+    /// parse tokens of info line
+    fn parse_tokens(
+        &mut self,
+        info: &str,
+        config: &InfoParserConfig,
+    ) -> Result<(), InfoParseError> {
         let mut ps = ParsingState::Info;
-        let mut pv_buff = String::new();
+        let mut pv: Vec<UciMove> = vec![];
         let mut pv_on = false;
+        // refuted move or cpu number of the line, if the line is a refutation or currline
+        let mut refuted: Option<UciMove> = None;
+        let mut currline_cpu: Option<usize> = None;
+        let mut line: Vec<UciMove> = vec![];
+        let mut wdl = Wdl::default();
+        // unknown key being skipped and its values
+        let mut unknown_key: Option<&str> = None;
+        let mut unknown_values: Vec<&str> = vec![];
+
+        let mut tokens = info.split(" ");
+
+        while let Some(token) = tokens.next() {
+            if let ParsingState::Unknown = ps {
+                if !INFO_KEYWORDS.contains(&token) {
+                    unknown_values.push(token);
+
+                    continue;
+                }
+
+                self.add_extra(unknown_key.take(), &mut unknown_values);
 
-        let allow_unknown_key = env_true("ALLOW_UNKNOWN_INFO_KEY");
+                ps = ParsingState::Key;
+            }
+
+            let in_line = matches!(
+                ps,
+                ParsingState::PvBestmove
+                    | ParsingState::PvPonder
+                    | ParsingState::PvRest
+                    | ParsingState::Refutation
+                    | ParsingState::RefutationLine
+                    | ParsingState::Currline
+                    | ParsingState::CurrlineMoves
+            );
+
+            // a keyword ends the pv, refutation or currline
+            if in_line && INFO_KEYWORDS.contains(&token) {
+                pv_on = false;
+
+                ps = ParsingState::Key;
+            }
 
-        for token in info.split(" ") {
             match ps {
                 ParsingState::Info => {
                     match token {
-                        "info" => ps = ParsingState::Key,
+                        "info" => {
+                            self.string = None;
+                            self.extras.clear();
+
+                            ps = ParsingState::Key
+                        }
                         _ => {
                             // not an info
                             return Ok(());
@@ -519,9 +719,16 @@ impl AnalysisInfo {
                     }
                 }
                 ParsingState::Key => {
-                    if (token == "string") || (token == "refutation") || (token == "currline") {
-                        // string, refutation and currline are not supported
-                        return Ok(());
+                    if token == "string" {
+                        // the rest of the line is the string
+                        self.string = Some(tokens.collect::<Vec<&str>>().join(" "));
+
+                        // a string line keeps the pv, unless a move list came before the string
+                        if pv.is_empty() && refuted.is_none() && currline_cpu.is_none() {
+                            return Ok(());
+                        }
+
+                        break;
                     }
 
                     ps = match token {
@@ -541,6 +748,7 @@ impl AnalysisInfo {
                         "nodes" => ParsingState::Nodes,
                         "multipv" => ParsingState::Multipv,
                         "score" => ParsingState::Score,
+                        "wdl" => ParsingState::WdlWin,
                         "currmove" => ParsingState::Currmove,
                         "currmovenumber" => ParsingState::Currmovenumber,
                         "hashfull" => ParsingState::Hashfull,
@@ -548,8 +756,12 @@ impl AnalysisInfo {
                         "tbhits" => ParsingState::Tbhits,
                         "cpuload" => ParsingState::Cpuload,
                         "pv" => ParsingState::PvBestmove,
+                        "refutation" => ParsingState::Refutation,
+                        "currline" => ParsingState::Currline,
                         _ => {
-                            if allow_unknown_key {
+                            if config.allow_unknown_key {
+                                unknown_key = Some(token);
+
                                 ParsingState::Unknown
                             } else {
                                 return Err(InfoParseError::InvalidKeyError(token.to_string()));
@@ -559,6 +771,7 @@ impl AnalysisInfo {
 
                     if let ParsingState::Score = ps {
                         self.scoretype = ScoreType::Exact;
+                        self.wdl = None;
                     }
                 }
                 ParsingState::Score => match token {
@@ -568,40 +781,32 @@ impl AnalysisInfo {
                     "lowerbound" => self.scoretype = ScoreType::Lowerbound,
                     _ => {
                         // not a valid score specifier
-                        return info_parse_error(InfoParseError::InvalidScoreSpecifier(
-                            token.to_string(),
-                        ));
+                        return Err(InfoParseError::InvalidScoreSpecifier(token.to_string()));
                     }
                 },
-                ParsingState::Unknown => {
-                    // ignore this token and hope for the best ( namely that it had a single token arg )
-                    warn!("unknown info key {}", token);
-
-                    ps = ParsingState::Key
-                }
                 _ => {
                     let mut keep_state = false;
 
                     match ps {
                         ParsingState::Depth => match token.parse::<usize>() {
                             Ok(depth) => self.depth = depth,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Seldepth => match token.parse::<usize>() {
                             Ok(seldepth) => self.seldepth = seldepth,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Time => match token.parse::<usize>() {
                             Ok(time) => self.time = time,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Nodes => match token.parse::<u64>() {
                             Ok(nodes) => self.nodes = nodes,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Multipv => match token.parse::<usize>() {
                             Ok(multipv) => self.multipv = multipv,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::ScoreCp => match token {
                             "upperbound" => {
@@ -616,7 +821,7 @@ impl AnalysisInfo {
                             }
                             _ => match token.parse::<i32>() {
                                 Ok(score_cp) => self.score = Score::Cp(score_cp),
-                                _ => return parse_number_error(ps, token),
+                                _ => return number_error(ps, token),
                             },
                         },
                         ParsingState::ScoreMate => match token {
@@ -632,58 +837,115 @@ impl AnalysisInfo {
                             }
                             _ => match token.parse::<i32>() {
                                 Ok(score_mate) => self.score = Score::Mate(score_mate),
-                                _ => return parse_number_error(ps, token),
+                                _ => return number_error(ps, token),
                             },
                         },
+                        ParsingState::WdlWin => match token.parse::<u32>() {
+                            Ok(win) => {
+                                wdl.win = win;
+
+                                keep_state = true;
+
+                                ps = ParsingState::WdlDraw
+                            }
+                            _ => return number_error(ps, token),
+                        },
+                        ParsingState::WdlDraw => match token.parse::<u32>() {
+                            Ok(draw) => {
+                                wdl.draw = draw;
+
+                                keep_state = true;
+
+                                ps = ParsingState::WdlLoss
+                            }
+                            _ => return number_error(ps, token),
+                        },
+                        ParsingState::WdlLoss => match token.parse::<u32>() {
+                            Ok(loss) => {
+                                wdl.loss = loss;
+
+                                self.wdl = Some(wdl);
+                            }
+                            _ => return number_error(ps, token),
+                        },
                         ParsingState::Currmove => {
-                            self.currmove.set(token);
+                            self.currmove = Some(parse_move(token)?);
                         }
                         ParsingState::Currmovenumber => match token.parse::<usize>() {
                             Ok(currmovenumber) => self.currmovenumber = currmovenumber,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Hashfull => match token.parse::<usize>() {
                             Ok(hashfull) => self.hashfull = hashfull,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Nps => match token.parse::<u64>() {
                             Ok(nps) => self.nps = nps,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Tbhits => match token.parse::<u64>() {
                             Ok(tbhits) => self.tbhits = tbhits,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::Cpuload => match token.parse::<usize>() {
                             Ok(cpuload) => self.cpuload = cpuload,
-                            _ => return parse_number_error(ps, token),
+                            _ => return number_error(ps, token),
                         },
                         ParsingState::PvBestmove => {
-                            pv_buff = pv_buff + token;
+                            let uci_move = parse_move(token)?;
+
+                            pv.push(uci_move);
 
-                            self.bestmove = UciBuff::from(token);
+                            self.bestmove = Some(uci_move);
 
-                            self.ponder.reset();
+                            self.ponder = None;
 
                             pv_on = true;
 
                             ps = ParsingState::PvPonder
                         }
                         ParsingState::PvPonder => {
-                            pv_buff = pv_buff + " " + token;
+                            let uci_move = parse_move(token)?;
 
-                            self.ponder = UciBuff::from(token);
+                            pv.push(uci_move);
+
+                            self.ponder = Some(uci_move);
 
                             ps = ParsingState::PvRest
                         }
-                        ParsingState::PvRest => pv_buff = pv_buff + " " + token,
+                        ParsingState::PvRest => pv.push(parse_move(token)?),
+                        ParsingState::Refutation => {
+                            refuted = Some(parse_move(token)?);
+
+                            pv_on = true;
+
+                            ps = ParsingState::RefutationLine
+                        }
+                        ParsingState::Currline => {
+                            // cpu number can be omitted if the engine uses a single cpu
+                            match token.parse::<usize>() {
+                                Ok(cpu) => currline_cpu = Some(cpu),
+                                _ => {
+                                    currline_cpu = Some(1);
+
+                                    line.push(parse_move(token)?);
+                                }
+                            }
+
+                            pv_on = true;
+
+                            ps = ParsingState::CurrlineMoves
+                        }
+                        ParsingState::RefutationLine | ParsingState::CurrlineMoves => {
+                            line.push(parse_move(token)?)
+                        }
                         _ => {
                             // should not happen
                         }
                     }
 
-                    // anything from key pv onwards should be added to pv
-                    // otherwise switch back to parsing key
+                    // anything from key pv, refutation or currline up to the next keyword
+                    // belongs to that line, otherwise switch back to parsing key
                     if (!pv_on) && (!keep_state) {
                         ps = ParsingState::Key;
                     }
@@ -691,36 +953,193 @@ impl AnalysisInfo {
             }
         }
 
-        self.pv.set_trim(pv_buff, ' ');
+        self.add_extra(unknown_key, &mut unknown_values);
+
+        // refutation and currline lines keep the pv of the search
+        if let Some(refuted) = refuted {
+            self.refutations.insert(refuted, line);
+        } else if let Some(cpu) = currline_cpu {
+            self.currlines.insert(cpu, line);
+        } else {
+            if let Some(max_pv_len) = config.max_pv_len {
+                pv.truncate(max_pv_len);
+            }
+
+            self.pv = pv;
+        }
 
         Ok(())
     }
 }
 
 #[test]
-fn set_trim() {
-    let mut x = PvBuff::new().set("e2e4");
+fn parse_error() {
+    let mut ai = AnalysisInfo::new();
+
+    let _ = ai.parse(
+        "info depth 3 score mate 5 nodes 3000000000 time 3000 nps 1000000 pv e2e4 e7e5 g1f3",
+    );
+
+    assert_eq!(ai.depth, 3);
+    assert_eq!(format!("{:?}", ai.score), format!("{:?}", Score::Mate(5)));
+    assert_eq!(ai.ponder(), UciMove::parse("e7e5").ok());
+}
 
-    assert_eq!(x.len, 4);
+#[test]
+fn multipv_snapshot() {
+    let mut snapshot = MultiPvSnapshot::new();
 
-    assert_eq!(String::from(x), "e2e4".to_string());
+    for line in &[
+        "info depth 12 multipv 1 score cp 30 pv e2e4 e7e5",
+        "info depth 12 multipv 2 score cp 20 pv d2d4 d7d5",
+        "info depth 12 currmove g1f3 currmovenumber 3",
+        "info depth 12 multipv 3 score cp 10 pv c2c4 e7e5",
+    ] {
+        let mut ai = AnalysisInfo::new();
 
-    x.set_trim("e2e4 e7e5 g1f3 b8c6", ' ');
+        let _ = ai.parse(line);
 
-    assert_eq!(x.len, 9);
+        snapshot.update(&ai);
+    }
 
-    assert_eq!(String::from(x), "e2e4 e7e5".to_string());
+    assert_eq!(snapshot.len(), 3);
+    assert_eq!(
+        snapshot.line(2).unwrap().bestmove(),
+        UciMove::parse("d2d4").ok()
+    );
+
+    let mut ai = AnalysisInfo::new();
+
+    let _ = ai.parse("info depth 13 multipv 1 score cp 25 pv e2e4 c7c5");
+
+    snapshot.update(&ai);
+
+    assert_eq!(snapshot.depth, 13);
+    assert_eq!(snapshot.len(), 1);
 }
 
 #[test]
-fn parse_error() {
+fn pv_round_trip() {
+    let line = "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7 f1e1 b7b5 a4b3";
+
     let mut ai = AnalysisInfo::new();
 
-    let _ = ai.parse(
-        "info depth 3 score mate 5 nodes 3000000000 time 3000 nps 1000000 pv e2e4 e7e5 g1f3",
+    let _ = ai.parse(format!("info depth 20 score cp 30 pv {}", line));
+
+    assert_eq!(ai.pv_moves().len(), 13);
+
+    let ai = AnalysisInfo::from_json(&ai.to_json().unwrap()).unwrap();
+
+    assert_eq!(ai.pv(), Some(line.to_string()));
+}
+
+#[test]
+fn string_refutation_currline() {
+    let mut ai = AnalysisInfo::new();
+
+    let _ = ai.parse("info depth 10 score cp 20 pv e2e4 e7e5");
+    let _ = ai.parse("info string NNUE evaluation using nn-62ef826d1a6d.nnue enabled");
+
+    assert_eq!(
+        ai.string,
+        Some("NNUE evaluation using nn-62ef826d1a6d.nnue enabled".to_string())
     );
 
-    assert_eq!(ai.depth, 3);
-    assert_eq!(format!("{:?}", ai.score), format!("{:?}", Score::Mate(5)));
-    assert_eq!(format!("{:?}", ai.ponder()), format!("{:?}", Some("e7e5")));
+    let _ = ai.parse("info refutation d1h5 g6h5");
+    let _ = ai.parse("info refutation f1c4");
+    let _ = ai.parse("info currline 2 e2e4 c7c5");
+    let _ = ai.parse("info currline d2d4");
+
+    assert_eq!(ai.string, None);
+    assert_eq!(
+        ai.refutation(UciMove::parse("d1h5").unwrap()),
+        Some(&[UciMove::parse("g6h5").unwrap()][..])
+    );
+    assert_eq!(
+        ai.refutation(UciMove::parse("f1c4").unwrap()),
+        Some(&[][..])
+    );
+    assert_eq!(ai.currline(2).map(|line| line.len()), Some(2));
+    assert_eq!(ai.currline(1), Some(&[UciMove::parse("d2d4").unwrap()][..]));
+    assert_eq!(ai.pv(), Some("e2e4 e7e5".to_string()));
+}
+
+#[test]
+fn wdl() {
+    let mut ai = AnalysisInfo::new();
+
+    let _ = ai.parse("info depth 20 score cp 35 wdl 412 520 68 nodes 1000 pv e2e4");
+
+    assert_eq!(
+        ai.wdl,
+        Some(Wdl {
+            win: 412,
+            draw: 520,
+            loss: 68
+        })
+    );
+    assert_eq!(ai.nodes, 1000);
+
+    let ai_json = AnalysisInfo::from_json(&ai.to_json().unwrap()).unwrap();
+
+    assert_eq!(ai_json.wdl, ai.wdl);
+
+    let _ = ai.parse("info depth 21 score cp 30 pv e2e4");
+
+    assert_eq!(ai.wdl, None);
+    assert!(ai.parse("info depth 21 score cp 30 wdl 500 x 0").is_err());
+}
+
+#[test]
+fn unknown_keys() {
+    let config = InfoParserConfig::new().allow_unknown_key(true);
+
+    let mut ai = AnalysisInfo::new();
+
+    let result = ai.parse_with(
+        "info depth 12 ebf 1.8 2.1 score cp 20 movesleft nodes 500 pv e2e4",
+        &config,
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(ai.extras.get("ebf"), Some(&"1.8 2.1".to_string()));
+    assert_eq!(ai.extras.get("movesleft"), Some(&"".to_string()));
+    assert_eq!(ai.nodes, 500);
+    assert_eq!(ai.pv(), Some("e2e4".to_string()));
+
+    let _ = ai.parse_with("info depth 13 score cp 25 nodes 600 pv e2e4", &config);
+
+    assert!(ai.extras.is_empty());
+
+    // a keyword after the pv is parsed as a key
+    assert!(ai.parse("info depth 5 pv e2e4 e7e5 nodes 10").is_ok());
+    assert_eq!(ai.depth, 5);
+    assert_eq!(ai.nodes, 10);
+    assert_eq!(ai.pv(), Some("e2e4 e7e5".to_string()));
+
+    assert!(ai
+        .parse("info depth 6 refutation d1h5 g6h5 score cp 10 string done")
+        .is_ok());
+    assert_eq!(
+        ai.refutation(UciMove::parse("d1h5").unwrap())
+            .map(|line| line.len()),
+        Some(1)
+    );
+    assert_eq!(ai.string, Some("done".to_string()));
+}
+
+#[test]
+fn parser_config() {
+    let config = InfoParserConfig::new().max_pv_len(2).log_errors(false);
+
+    let mut ai = AnalysisInfo::new();
+
+    let _ = ai.parse_with("info depth 5 score cp 10 pv e2e4 e7e5 g1f3 b8c6", &config);
+
+    assert_eq!(ai.pv(), Some("e2e4 e7e5".to_string()));
+    assert_eq!(ai.ponder(), UciMove::parse("e7e5").ok());
+    assert!(matches!(
+        ai.parse_with("info depth 5 ebf 2", &config),
+        Err(InfoParseError::InvalidKeyError(_))
+    ));
 }
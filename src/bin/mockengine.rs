@@ -0,0 +1,312 @@
+//! mock uci engine for testing without a real engine binary
+//!
+//! by default it answers by rules :
+//! uci, isready, setoption, ucinewgame, position, go, stop, ponderhit and quit
+//! are understood, a finite go reports an info string, one info line per depth and multipv line,
+//! another info string then bestmove, an infinite or ponder go reports infos and waits for stop or ponderhit
+//!
+//! options :
+//!
+//! --transcript <file> : replay the engine lines of a recorded transcript,
+//! every command is answered with the lines that followed the same command in the transcript,
+//! commands not found in the transcript are answered by rules
+//!
+//! --bestmove <move> : best move to report ( default e2e4, use "(none)" for no legal move )
+//!
+//! --delay <ms> : time to think before reporting bestmove for a finite go
+//!
+//! --crash-on-go : exit with code 3 when receiving go
+//!
+//! --ignore-stop : do not answer stop
+//!
+//! --pid-file <file> : write process id to file at startup
+//!
+//! --stderr : write a line to stderr at startup and when receiving go
+
+use std::io::{BufRead, Write};
+
+use uciengine::transcript::*;
+
+/// depth searched by a go without depth
+const DEFAULT_DEPTH: usize = 3;
+
+/// mock engine settings
+struct Settings {
+    bestmove: String,
+    delay: u64,
+    crash_on_go: bool,
+    ignore_stop: bool,
+    stderr: bool,
+}
+
+/// command of a recorded transcript with the engine lines that followed it
+struct Exchange {
+    command: String,
+    responses: Vec<String>,
+    replayed: bool,
+}
+
+/// mock engine state
+struct MockEngine {
+    settings: Settings,
+    exchanges: Vec<Exchange>,
+    multipv: usize,
+    show_wdl: bool,
+    searching: bool,
+    pondering: bool,
+}
+
+/// write line to stdout
+fn send<T: AsRef<str>>(line: T) {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    let _ = writeln!(stdout, "{}", line.as_ref());
+    let _ = stdout.flush();
+}
+
+/// split transcript into exchanges
+fn exchanges(transcript: Vec<TranscriptLine>) -> Vec<Exchange> {
+    let mut exchanges: Vec<Exchange> = vec![];
+
+    // engine lines before the first command are sent at startup
+    let mut startup = Exchange {
+        command: String::new(),
+        responses: vec![],
+        replayed: false,
+    };
+
+    for transcript_line in transcript {
+        match transcript_line.direction {
+            Direction::ToEngine => exchanges.push(Exchange {
+                command: transcript_line.line,
+                responses: vec![],
+                replayed: false,
+            }),
+            Direction::FromEngine => match exchanges.last_mut() {
+                Some(exchange) => exchange.responses.push(transcript_line.line),
+                _ => startup.responses.push(transcript_line.line),
+            },
+        }
+    }
+
+    for line in startup.responses {
+        send(line);
+    }
+
+    exchanges
+}
+
+/// mock engine implementation
+impl MockEngine {
+    /// replay the responses of the first exchange of command not replayed yet,
+    /// returns false if there is no such exchange
+    fn replay(&mut self, command: &str) -> bool {
+        match self
+            .exchanges
+            .iter_mut()
+            .find(|exchange| (!exchange.replayed) && (exchange.command == command))
+        {
+            Some(exchange) => {
+                exchange.replayed = true;
+
+                for line in &exchange.responses {
+                    send(line);
+                }
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// send info lines for depth
+    fn send_infos(&self, depth: usize) {
+        // a position without legal moves is reported as mated, without pv
+        if (self.settings.bestmove == "(none)") || (self.settings.bestmove == "0000") {
+            send("info depth 0 score mate 0");
+
+            return;
+        }
+
+        for multipv in 1..=self.multipv {
+            let score_cp = 50 - (multipv as i64) * 10;
+
+            let wdl = match self.show_wdl {
+                true => format!(" wdl {} {} {}", 300 + score_cp, 600, 100 - score_cp),
+                _ => String::new(),
+            };
+
+            send(format!(
+                "info depth {} seldepth {} multipv {} score cp {}{} nodes {} nps 1000 time {} pv {}",
+                depth,
+                depth,
+                multipv,
+                score_cp,
+                wdl,
+                depth * 100,
+                depth * 10,
+                self.settings.bestmove
+            ));
+        }
+    }
+
+    /// send bestmove
+    fn send_bestmove(&mut self) {
+        self.searching = false;
+        self.pondering = false;
+
+        send("info string search done");
+        send(format!("bestmove {}", self.settings.bestmove));
+    }
+
+    /// answer command by rules, returns false on quit
+    fn answer(&mut self, command: &str) -> bool {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+
+        match tokens.first().copied() {
+            Some("uci") => {
+                send("id name mockengine");
+                send("id author uciengine");
+                send("option name Hash type spin default 16 min 1 max 1024");
+                send("option name Threads type spin default 1 min 1 max 512");
+                send("option name MultiPV type spin default 1 min 1 max 500");
+                send("option name Ponder type check default false");
+                send("option name UCI_ShowWDL type check default false");
+                send("option name Clear Hash type button");
+                send("option name UCI_Variant type combo default chess var chess var atomic");
+                send("uciok");
+            }
+            Some("isready") => send("readyok"),
+            Some("setoption") => {
+                // setoption name <name> value <value>
+                match (tokens.get(2), tokens.get(4)) {
+                    (Some(&"MultiPV"), Some(value)) => self.multipv = value.parse().unwrap_or(1),
+                    (Some(&"UCI_ShowWDL"), Some(value)) => self.show_wdl = *value == "true",
+                    _ => {}
+                }
+            }
+            Some("go") => {
+                if self.settings.crash_on_go {
+                    std::process::exit(3);
+                }
+
+                if self.settings.stderr {
+                    eprintln!("mockengine go");
+                }
+
+                let depth = tokens
+                    .iter()
+                    .position(|token| *token == "depth")
+                    .and_then(|index| tokens.get(index + 1))
+                    .and_then(|depth| depth.parse().ok())
+                    .unwrap_or(DEFAULT_DEPTH);
+
+                self.searching = true;
+                self.pondering = tokens.contains(&"ponder");
+
+                send(format!("info string searching to depth {}", depth));
+
+                if self.pondering || tokens.contains(&"infinite") {
+                    self.send_infos(1);
+
+                    return true;
+                }
+
+                for depth in 1..=depth {
+                    self.send_infos(depth);
+                }
+
+                std::thread::sleep(std::time::Duration::from_millis(self.settings.delay));
+
+                self.send_bestmove();
+            }
+            Some("ponderhit") if self.pondering => self.send_bestmove(),
+            Some("stop") if self.searching && (!self.settings.ignore_stop) => self.send_bestmove(),
+            Some("quit") => return false,
+            _ => {}
+        }
+
+        true
+    }
+}
+
+fn main() {
+    let mut settings = Settings {
+        bestmove: "e2e4".to_string(),
+        delay: 0,
+        crash_on_go: false,
+        ignore_stop: false,
+        stderr: false,
+    };
+
+    let mut transcript: Vec<TranscriptLine> = vec![];
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--transcript" => {
+                let path = args.next().expect("--transcript needs a file");
+
+                let content = std::fs::read_to_string(&path).expect("could not read transcript");
+
+                transcript = parse_transcript(content).expect("invalid transcript");
+            }
+            "--bestmove" => settings.bestmove = args.next().expect("--bestmove needs a move"),
+            "--delay" => {
+                settings.delay = args
+                    .next()
+                    .and_then(|delay| delay.parse().ok())
+                    .expect("--delay needs milliseconds")
+            }
+            "--crash-on-go" => settings.crash_on_go = true,
+            "--ignore-stop" => settings.ignore_stop = true,
+            "--stderr" => {
+                settings.stderr = true;
+
+                eprintln!("mockengine started");
+            }
+            "--pid-file" => {
+                let path = args.next().expect("--pid-file needs a file");
+
+                std::fs::write(path, std::process::id().to_string())
+                    .expect("could not write pid file");
+            }
+            _ => {
+                eprintln!("unknown argument {}", arg);
+
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let mut engine = MockEngine {
+        settings,
+        exchanges: exchanges(transcript),
+        multipv: 1,
+        show_wdl: false,
+        searching: false,
+        pondering: false,
+    };
+
+    let stdin = std::io::stdin();
+
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            _ => break,
+        };
+
+        let command = line.trim();
+
+        // quit is always obeyed, even if it was recorded
+        if engine.replay(command) && (command != "quit") {
+            continue;
+        }
+
+        if !engine.answer(command) {
+            break;
+        }
+    }
+}
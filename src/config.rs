@@ -0,0 +1,218 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+
+use crate::analysis::*;
+use crate::transcript::*;
+
+/// how the stderr of the engine process is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StderrMode {
+    /// engine writes to the stderr of this process
+    Inherit,
+    /// stderr is discarded
+    Discard,
+    /// stderr lines are logged and broadcast on the engine handle
+    Capture,
+}
+
+/// engine process configuration
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    /// path of the engine executable
+    path: String,
+    /// command line arguments
+    args: Vec<String>,
+    /// working directory, None for the working directory of this process
+    cwd: Option<PathBuf>,
+    /// environment variables as key value pairs, added to the environment of this process
+    env: Vec<(String, String)>,
+    /// stderr mode
+    stderr: StderrMode,
+    /// protocol transcript target, None for no transcript
+    transcript: Option<TranscriptTarget>,
+    /// info parser config, None for the config of the environment
+    info_parser: Option<InfoParserConfig>,
+}
+
+/// engine config implementation
+impl EngineConfig {
+    /// create new engine config for executable path, with no arguments and stderr inherited
+    pub fn new<T>(path: T) -> Self
+    where
+        T: core::fmt::Display,
+    {
+        Self {
+            path: path.to_string(),
+            args: vec![],
+            cwd: None,
+            env: vec![],
+            stderr: StderrMode::Inherit,
+            transcript: None,
+            info_parser: None,
+        }
+    }
+
+    /// add command line argument and return self
+    #[must_use]
+    pub fn arg<T>(mut self, arg: T) -> Self
+    where
+        T: core::fmt::Display,
+    {
+        self.args.push(arg.to_string());
+
+        self
+    }
+
+    /// add command line arguments and return self
+    #[must_use]
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: core::fmt::Display,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.to_string()));
+
+        self
+    }
+
+    /// set working directory and return self
+    #[must_use]
+    pub fn cwd<T>(mut self, cwd: T) -> Self
+    where
+        T: Into<PathBuf>,
+    {
+        self.cwd = Some(cwd.into());
+
+        self
+    }
+
+    /// set environment variable and return self
+    #[must_use]
+    pub fn env<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: core::fmt::Display,
+        V: core::fmt::Display,
+    {
+        self.env.push((key.to_string(), value.to_string()));
+
+        self
+    }
+
+    /// set stderr mode and return self
+    #[must_use]
+    pub fn stderr(mut self, stderr: StderrMode) -> Self {
+        self.stderr = stderr;
+
+        self
+    }
+
+    /// record protocol transcript to target and return self
+    #[must_use]
+    pub fn transcript(mut self, target: TranscriptTarget) -> Self {
+        self.transcript = Some(target);
+
+        self
+    }
+
+    /// set info parser config and return self
+    #[must_use]
+    pub fn info_parser(mut self, info_parser: InfoParserConfig) -> Self {
+        self.info_parser = Some(info_parser);
+
+        self
+    }
+
+    /// get executable path
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// get stderr mode
+    pub fn stderr_mode(&self) -> StderrMode {
+        self.stderr
+    }
+
+    /// get transcript target
+    pub fn transcript_target(&self) -> Option<&TranscriptTarget> {
+        self.transcript.as_ref()
+    }
+
+    /// get info parser config, the config of the environment if none was set
+    pub fn info_parser_config(&self) -> InfoParserConfig {
+        self.info_parser
+            .unwrap_or_else(InfoParserConfig::env_default)
+    }
+
+    /// create command for spawning the engine process, with stdin and stdout piped
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(&self.path);
+
+        command
+            .args(&self.args)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdout(Stdio::piped())
+            .stdin(Stdio::piped())
+            .kill_on_drop(true);
+
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        match self.stderr {
+            StderrMode::Inherit => command.stderr(Stdio::inherit()),
+            StderrMode::Discard => command.stderr(Stdio::null()),
+            StderrMode::Capture => command.stderr(Stdio::piped()),
+        };
+
+        command
+    }
+}
+
+/// implement From<&str> for EngineConfig
+impl std::convert::From<&str> for EngineConfig {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+/// implement From<String> for EngineConfig
+impl std::convert::From<String> for EngineConfig {
+    fn from(path: String) -> Self {
+        Self::new(path)
+    }
+}
+
+/// implement From<&String> for EngineConfig
+impl std::convert::From<&String> for EngineConfig {
+    fn from(path: &String) -> Self {
+        Self::new(path)
+    }
+}
+
+#[test]
+fn config_command() {
+    let config = EngineConfig::new("lc0")
+        .arg("--weights=net.pb.gz")
+        .args(vec!["--threads=2"])
+        .cwd("/engines")
+        .env("CUDA_VISIBLE_DEVICES", 0)
+        .stderr(StderrMode::Discard)
+        .info_parser(InfoParserConfig::new().max_pv_len(4));
+
+    let command = config.command();
+    let command = command.as_std();
+
+    assert_eq!(command.get_program(), "lc0");
+    assert_eq!(
+        command.get_args().collect::<Vec<_>>(),
+        vec!["--weights=net.pb.gz", "--threads=2"]
+    );
+    assert_eq!(
+        command.get_current_dir(),
+        Some(std::path::Path::new("/engines"))
+    );
+    assert_eq!(config.stderr_mode(), StderrMode::Discard);
+    assert_eq!(config.info_parser_config().max_pv_len, Some(4));
+}
@@ -0,0 +1,323 @@
+use thiserror::Error;
+
+use crate::analysis::*;
+use crate::options::*;
+use crate::ucimove::*;
+
+/// MessageParseError captures possible engine message parsing errors
+#[derive(Error, Debug)]
+pub enum MessageParseError {
+    #[error("empty engine message")]
+    EmptyMessageError,
+    #[error("unknown engine message '{0}'")]
+    UnknownMessageError(String),
+    #[error("invalid id message '{0}'")]
+    InvalidIdError(String),
+    #[error("invalid bestmove message '{0}'")]
+    InvalidBestMoveError(String),
+    #[error("invalid status in '{0}'")]
+    InvalidStatusError(String),
+    #[error("invalid option message : {0}")]
+    OptionError(#[from] OptionParseError),
+    #[error("invalid info message : {0}")]
+    InfoError(#[from] InfoParseError),
+}
+
+// http://wbec-ridderkerk.nl/html/UCIProtocol.html
+//
+// Engine to GUI:
+// --------------
+//
+// * id
+// 	* name
+// 		this must be sent after receiving the "uci" command to identify the engine,
+// 		e.g. "id name Shredder X.Y\n"
+// 	* author
+// 		this must be sent after receiving the "uci" command to identify the engine,
+// 		e.g. "id author Stefan MK\n"
+// * uciok
+// 	Must be sent after the id and optional options to tell the GUI that the engine
+// 	has sent all infos and is ready in uci mode.
+// * readyok
+// 	This must be sent when the engine has received an "isready" command and has
+// 	processed all input and is ready to accept new commands now.
+// * bestmove  [ ponder  ]
+// 	the engine has stopped searching and found the move  best in this position.
+// 	the engine can send the move it likes to ponder on.
+// * copyprotection
+// 	this is needed for copyprotected engines. After the uciok command the engine can tell the GUI,
+// 	that it will check the copy protection now. This is done by "copyprotection checking".
+// 	If the check is ok the engine should send "copyprotection ok", otherwise "copyprotection error".
+// * registration
+// 	this is needed for engines that need a username and/or a code to function with all features.
+// 	Analog to the "copyprotection" command the engine can send "registration checking"
+// 	after the uciok command followed by either "registration ok" or "registration error".
+// * info
+// 	the engine wants to send infos to the GUI.
+// * option
+// 	This command tells the GUI which parameters can be changed in the engine.
+
+/// status of a copyprotection or registration check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// engine is checking
+    Checking,
+    /// check passed
+    Ok,
+    /// check failed
+    Error,
+}
+
+/// check status implementation
+impl CheckStatus {
+    /// parse check status
+    pub fn parse<T: AsRef<str>>(status: T) -> Option<Self> {
+        match status.as_ref() {
+            "checking" => Some(CheckStatus::Checking),
+            "ok" => Some(CheckStatus::Ok),
+            "error" => Some(CheckStatus::Error),
+            _ => None,
+        }
+    }
+}
+
+/// implement Display for CheckStatus
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckStatus::Checking => write!(f, "checking"),
+            CheckStatus::Ok => write!(f, "ok"),
+            CheckStatus::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// message sent by the engine to the gui
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum EngineMessage {
+    /// id name
+    IdName(String),
+    /// id author
+    IdAuthor(String),
+    /// uciok
+    UciOk,
+    /// readyok
+    ReadyOk,
+    /// bestmove, None for bestmove (none) sent when there is no legal move
+    BestMove {
+        bestmove: Option<UciMove>,
+        ponder: Option<UciMove>,
+    },
+    /// copyprotection
+    CopyProtection(CheckStatus),
+    /// registration
+    Registration(CheckStatus),
+    /// info, the analysis info updated by the line and the text after info
+    Info { info: AnalysisInfo, text: String },
+    /// option
+    Option(EngineOption),
+}
+
+/// engine message implementation
+impl EngineMessage {
+    /// parse engine message, an info line is parsed into a new analysis info
+    /// with the default parser config
+    pub fn parse<T: AsRef<str>>(line: T) -> Result<Self, MessageParseError> {
+        Self::parse_update(line, &mut AnalysisInfo::new(), &InfoParserConfig::default())
+    }
+
+    /// parse engine message, an info line updates ai with parser config
+    /// and the message carries the updated analysis info
+    pub fn parse_update<T: AsRef<str>>(
+        line: T,
+        ai: &mut AnalysisInfo,
+        config: &InfoParserConfig,
+    ) -> Result<Self, MessageParseError> {
+        let line = line.as_ref().trim_end();
+
+        let mut tokens = line.split_whitespace();
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            _ => return Err(MessageParseError::EmptyMessageError),
+        };
+
+        // text after the keyword and the space that follows it
+        let rest = line.trim_start()[keyword.len()..]
+            .strip_prefix(' ')
+            .unwrap_or("");
+
+        match keyword {
+            "id" => match tokens.next() {
+                Some("name") => Ok(EngineMessage::IdName(id_value(line, rest, "name")?)),
+                Some("author") => Ok(EngineMessage::IdAuthor(id_value(line, rest, "author")?)),
+                _ => Err(MessageParseError::InvalidIdError(line.to_string())),
+            },
+            "uciok" => Ok(EngineMessage::UciOk),
+            "readyok" => Ok(EngineMessage::ReadyOk),
+            "bestmove" => {
+                let bestmove = match tokens.next() {
+                    Some("(none)") => None,
+                    Some(bestmove) => Some(parse_message_move(line, bestmove)?),
+                    None => return Err(MessageParseError::InvalidBestMoveError(line.to_string())),
+                };
+
+                // the best move is kept whatever follows it, a missing, (none), null
+                // or invalid ponder is no ponder
+                let ponder = match (tokens.next(), tokens.next()) {
+                    (Some("ponder"), Some(ponder)) => UciMove::parse(ponder)
+                        .ok()
+                        .filter(|ponder| !ponder.is_null()),
+                    _ => None,
+                };
+
+                Ok(EngineMessage::BestMove { bestmove, ponder })
+            }
+            "copyprotection" => Ok(EngineMessage::CopyProtection(check_status(line, rest)?)),
+            "registration" => Ok(EngineMessage::Registration(check_status(line, rest)?)),
+            "info" => {
+                ai.parse_with(line, config)?;
+
+                Ok(EngineMessage::Info {
+                    info: ai.clone(),
+                    text: rest.to_string(),
+                })
+            }
+            "option" => Ok(EngineMessage::Option(EngineOption::parse(line)?)),
+            _ => Err(MessageParseError::UnknownMessageError(line.to_string())),
+        }
+    }
+}
+
+/// value of id key, the rest of the line after the key
+fn id_value(line: &str, rest: &str, key: &str) -> Result<String, MessageParseError> {
+    match rest
+        .strip_prefix(key)
+        .and_then(|value| value.strip_prefix(' '))
+    {
+        Some(value) if !value.is_empty() => Ok(value.to_string()),
+        _ => Err(MessageParseError::InvalidIdError(line.to_string())),
+    }
+}
+
+/// parse move of bestmove message
+fn parse_message_move(line: &str, token: &str) -> Result<UciMove, MessageParseError> {
+    UciMove::parse(token).map_err(|_| MessageParseError::InvalidBestMoveError(line.to_string()))
+}
+
+/// parse check status of copyprotection or registration message
+fn check_status(line: &str, rest: &str) -> Result<CheckStatus, MessageParseError> {
+    CheckStatus::parse(rest).ok_or_else(|| MessageParseError::InvalidStatusError(line.to_string()))
+}
+
+/// implement Display for EngineMessage, formatted as sent by the engine
+impl std::fmt::Display for EngineMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineMessage::IdName(name) => write!(f, "id name {}", name),
+            EngineMessage::IdAuthor(author) => write!(f, "id author {}", author),
+            EngineMessage::UciOk => write!(f, "uciok"),
+            EngineMessage::ReadyOk => write!(f, "readyok"),
+            EngineMessage::BestMove { bestmove, ponder } => {
+                match bestmove {
+                    Some(bestmove) => write!(f, "bestmove {}", bestmove)?,
+                    None => write!(f, "bestmove (none)")?,
+                }
+
+                match ponder {
+                    Some(ponder) => write!(f, " ponder {}", ponder),
+                    None => Ok(()),
+                }
+            }
+            EngineMessage::CopyProtection(status) => write!(f, "copyprotection {}", status),
+            EngineMessage::Registration(status) => write!(f, "registration {}", status),
+            EngineMessage::Info { text, .. } if text.is_empty() => write!(f, "info"),
+            EngineMessage::Info { text, .. } => write!(f, "info {}", text),
+            EngineMessage::Option(option) => write!(f, "{}", option),
+        }
+    }
+}
+
+#[test]
+fn parse_and_display() {
+    for line in &[
+        "id name Stockfish 16",
+        "id author the Stockfish developers (see AUTHORS file)",
+        "uciok",
+        "readyok",
+        "bestmove e2e4 ponder e7e5",
+        "bestmove e7e8q",
+        "bestmove (none)",
+        "bestmove 0000",
+        "copyprotection checking",
+        "registration error",
+        "info depth 20 seldepth 28 multipv 1 score cp 35 wdl 412 520 68 nodes 1000 pv e2e4",
+        "info string NNUE evaluation using nn-62ef826d1a6d.nnue enabled",
+        "option name Skill Level type spin default 20 min 0 max 20",
+        "option name UCI_Variant type combo default chess var chess var atomic",
+        "option name SyzygyPath type string default <empty>",
+        "option name Debug Log File type string default",
+        "option name Ponder type check",
+        "option name Threads type spin min 1 max 512 default 1",
+        "option name UCI_Variant type combo var chess var atomic default chess",
+        "option name Hash  type spin default 16 min 1 max 1024",
+        "option name Clear Hash type button",
+    ] {
+        let message = EngineMessage::parse(line).unwrap();
+
+        assert_eq!(message.to_string(), *line);
+    }
+
+    assert_eq!(
+        EngineMessage::parse("bestmove (none)").unwrap(),
+        EngineMessage::BestMove {
+            bestmove: None,
+            ponder: None
+        }
+    );
+
+    match EngineMessage::parse("info depth 20 score mate 3 pv e2e4 e7e5").unwrap() {
+        EngineMessage::Info { info, .. } => {
+            assert_eq!(info.depth, 20);
+            assert_eq!(info.score, Score::Mate(3));
+            assert_eq!(info.pv(), Some("e2e4 e7e5".to_string()));
+        }
+        message => panic!("not an info message {:?}", message),
+    }
+
+    assert!(matches!(
+        EngineMessage::parse("info depth x"),
+        Err(MessageParseError::InfoError(_))
+    ));
+    for line in &[
+        "bestmove e2e4 ponder (none)",
+        "bestmove e2e4 ponder",
+        "bestmove e2e4 ponder 0000",
+    ] {
+        assert_eq!(
+            EngineMessage::parse(line).unwrap(),
+            EngineMessage::BestMove {
+                bestmove: UciMove::parse("e2e4").ok(),
+                ponder: None
+            }
+        );
+    }
+
+    assert!(matches!(
+        EngineMessage::parse("bestmove e2e9"),
+        Err(MessageParseError::InvalidBestMoveError(_))
+    ));
+    assert!(matches!(
+        EngineMessage::parse("id nickname sf"),
+        Err(MessageParseError::InvalidIdError(_))
+    ));
+    assert!(matches!(
+        EngineMessage::parse("copyprotection maybe"),
+        Err(MessageParseError::InvalidStatusError(_))
+    ));
+    assert!(matches!(
+        EngineMessage::parse("Stockfish 16 by the Stockfish developers"),
+        Err(MessageParseError::UnknownMessageError(_))
+    ));
+}
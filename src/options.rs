@@ -0,0 +1,451 @@
+use thiserror::Error;
+
+/// OptionParseError captures possible option parsing errors
+#[derive(Error, Debug)]
+pub enum OptionParseError {
+    #[error("not an option line '{0}'")]
+    NotAnOptionError(String),
+    #[error("option has no name in '{0}'")]
+    MissingNameError(String),
+    #[error("option '{0}' has no type")]
+    MissingTypeError(String),
+    #[error("option '{0}' has invalid type '{1}'")]
+    InvalidTypeError(String, String),
+    #[error("option '{0}' has no '{1}'")]
+    MissingValueError(String, &'static str),
+    #[error("option '{0}' has invalid '{1}' value '{2}'")]
+    InvalidValueError(String, &'static str, String),
+}
+
+/// OptionValueError captures possible errors of setting an option
+#[derive(Error, Debug)]
+pub enum OptionValueError {
+    #[error("unknown option '{0}'")]
+    UnknownOptionError(String),
+    #[error("option '{0}' value '{1}' is not a number")]
+    NotANumberError(String, String),
+    #[error("option '{0}' value {1} is out of range {2} ..= {3}")]
+    OutOfRangeError(String, i64, i64, i64),
+    #[error("option '{0}' value '{1}' is not one of {2:?}")]
+    InvalidVarError(String, String, Vec<String>),
+    #[error("option '{0}' value '{1}' is not a boolean")]
+    NotABooleanError(String, String),
+}
+
+// http://wbec-ridderkerk.nl/html/UCIProtocol.html
+//
+// * option
+// 	This command tells the GUI which parameters can be changed in the engine.
+// 	This should be sent once at engine startup after the "uci" and the "id" commands
+// 	if any parameter can be changed in the engine.
+// 	* name
+// 		The option has the name id.
+// 	* type
+// 		The option has type t.
+// 		There are 5 different types of options the engine can send
+// 		* check
+// 			a checkbox that can either be true or false
+// 		* spin
+// 			a spin wheel that can be an integer in a certain range
+// 		* combo
+// 			a combo box that can have different predefined strings as a value
+// 		* button
+// 			a button that can be pressed to send a command to the engine
+// 		* string
+// 			a text field that has a string as a value,
+// 			an empty string has the value "<empty>"
+// 	* default
+// 		the default value of this parameter is x
+// 	* min
+// 		the minimum value of this parameter is x
+// 	* max
+// 		the maximum value of this parameter is x
+// 	* var
+// 		a predefined value of this parameter is x
+
+/// empty string as sent by the engine
+const EMPTY_STRING: &str = "<empty>";
+
+/// option kind with the values declared for it
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionKind {
+    /// checkbox, either true or false
+    Check { default: bool },
+    /// integer in the range min ..= max
+    Spin { default: i64, min: i64, max: i64 },
+    /// one of the predefined vars
+    Combo { default: String, vars: Vec<String> },
+    /// button, has no value
+    Button,
+    /// text field
+    String { default: String },
+}
+
+/// option declared by the engine
+#[derive(Debug, Clone)]
+pub struct EngineOption {
+    /// option name
+    pub name: String,
+    /// option kind
+    pub kind: OptionKind,
+    /// option line as sent by the engine
+    line: String,
+}
+
+/// implement PartialEq for EngineOption, options declaring the same name and kind are equal
+impl PartialEq for EngineOption {
+    fn eq(&self, other: &Self) -> bool {
+        (self.name == other.name) && (self.kind == other.kind)
+    }
+}
+
+/// option parsing state
+#[derive(Debug)]
+enum OptionParsingState {
+    Option,
+    Name,
+    Type,
+    Default,
+    Min,
+    Max,
+    Var,
+}
+
+/// engine option implementation
+impl EngineOption {
+    /// parse option line
+    pub fn parse<T: AsRef<str>>(line: T) -> Result<Self, OptionParseError> {
+        let line = line.as_ref();
+        let mut ps = OptionParsingState::Option;
+
+        let mut name: Vec<&str> = vec![];
+        let mut kind: Option<&str> = None;
+        let mut default: Option<Vec<&str>> = None;
+        let mut min: Option<&str> = None;
+        let mut max: Option<&str> = None;
+        let mut vars: Vec<Vec<&str>> = vec![];
+
+        for token in line.split_whitespace() {
+            match ps {
+                OptionParsingState::Option => match token {
+                    "option" => ps = OptionParsingState::Name,
+                    _ => return Err(OptionParseError::NotAnOptionError(line.to_string())),
+                },
+                OptionParsingState::Name => match token {
+                    // only type can end the name, the name itself may contain spaces
+                    "type" => ps = OptionParsingState::Type,
+                    "name" if name.is_empty() => {}
+                    _ => name.push(token),
+                },
+                _ => {
+                    let next_ps = match token {
+                        "default" => Some(OptionParsingState::Default),
+                        "min" => Some(OptionParsingState::Min),
+                        "max" => Some(OptionParsingState::Max),
+                        "var" => Some(OptionParsingState::Var),
+                        _ => None,
+                    };
+
+                    if let Some(next_ps) = next_ps {
+                        match next_ps {
+                            OptionParsingState::Default => default = Some(vec![]),
+                            OptionParsingState::Var => vars.push(vec![]),
+                            _ => {}
+                        }
+
+                        ps = next_ps;
+
+                        continue;
+                    }
+
+                    match ps {
+                        OptionParsingState::Type => kind = Some(token),
+                        OptionParsingState::Default => {
+                            if let Some(default) = default.as_mut() {
+                                default.push(token);
+                            }
+                        }
+                        OptionParsingState::Min => min = Some(token),
+                        OptionParsingState::Max => max = Some(token),
+                        OptionParsingState::Var => {
+                            if let Some(var) = vars.last_mut() {
+                                var.push(token);
+                            }
+                        }
+                        _ => {
+                            // should not happen
+                        }
+                    }
+                }
+            }
+        }
+
+        if name.is_empty() {
+            return Err(OptionParseError::MissingNameError(line.to_string()));
+        }
+
+        let name = name.join(" ");
+
+        let default = default.map(|default| default.join(" "));
+
+        let kind = match kind {
+            Some("check") => match default.as_deref() {
+                Some("true") => OptionKind::Check { default: true },
+                Some("false") | None => OptionKind::Check { default: false },
+                Some(value) => {
+                    return Err(OptionParseError::InvalidValueError(
+                        name,
+                        "default",
+                        value.to_string(),
+                    ))
+                }
+            },
+            Some("spin") => {
+                let default = parse_spin_value(&name, "default", default.as_deref())?;
+                let min = parse_spin_value(&name, "min", min)?;
+                let max = parse_spin_value(&name, "max", max)?;
+
+                OptionKind::Spin { default, min, max }
+            }
+            Some("combo") => OptionKind::Combo {
+                default: string_value(default),
+                vars: vars.into_iter().map(|var| var.join(" ")).collect(),
+            },
+            Some("button") => OptionKind::Button,
+            Some("string") => OptionKind::String {
+                default: string_value(default),
+            },
+            Some(kind) => return Err(OptionParseError::InvalidTypeError(name, kind.to_string())),
+            None => return Err(OptionParseError::MissingTypeError(name)),
+        };
+
+        Ok(Self {
+            name,
+            kind,
+            line: line.trim_end().to_string(),
+        })
+    }
+
+    /// check if value is valid for this option ( values are case insensitive )
+    pub fn validate<T: AsRef<str>>(&self, value: T) -> Result<(), OptionValueError> {
+        let value = value.as_ref();
+
+        match &self.kind {
+            OptionKind::Check { .. } => {
+                if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+                    Ok(())
+                } else {
+                    Err(OptionValueError::NotABooleanError(
+                        self.name.clone(),
+                        value.to_string(),
+                    ))
+                }
+            }
+            OptionKind::Spin { min, max, .. } => match value.parse::<i64>() {
+                Ok(number) if (number >= *min) && (number <= *max) => Ok(()),
+                Ok(number) => Err(OptionValueError::OutOfRangeError(
+                    self.name.clone(),
+                    number,
+                    *min,
+                    *max,
+                )),
+                _ => Err(OptionValueError::NotANumberError(
+                    self.name.clone(),
+                    value.to_string(),
+                )),
+            },
+            OptionKind::Combo { vars, .. } => {
+                if vars.iter().any(|var| var.eq_ignore_ascii_case(value)) {
+                    Ok(())
+                } else {
+                    Err(OptionValueError::InvalidVarError(
+                        self.name.clone(),
+                        value.to_string(),
+                        vars.clone(),
+                    ))
+                }
+            }
+            OptionKind::Button | OptionKind::String { .. } => Ok(()),
+        }
+    }
+}
+
+/// implement Display for EngineOption, the option line sent by the engine,
+/// or the option formatted as the engine would send it if it was changed since
+impl std::fmt::Display for EngineOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if EngineOption::parse(&self.line).ok().as_ref() == Some(self) {
+            return write!(f, "{}", self.line);
+        }
+
+        write!(f, "option name {} type ", self.name)?;
+
+        match &self.kind {
+            OptionKind::Check { default } => write!(f, "check default {}", default),
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            }
+            OptionKind::Combo { default, vars } => {
+                write!(f, "combo default {}", display_string_value(default))?;
+
+                for var in vars {
+                    write!(f, " var {}", var)?;
+                }
+
+                Ok(())
+            }
+            OptionKind::Button => write!(f, "button"),
+            OptionKind::String { default } => {
+                write!(f, "string default {}", display_string_value(default))
+            }
+        }
+    }
+}
+
+/// find option by name ( option names are case insensitive )
+pub fn find_option<T: AsRef<str>>(options: &[EngineOption], name: T) -> Option<&EngineOption> {
+    let name = name.as_ref();
+
+    options
+        .iter()
+        .find(|option| option.name.eq_ignore_ascii_case(name))
+}
+
+/// check if option name and value are valid according to the declared options
+pub fn validate_option<K: AsRef<str>, V: AsRef<str>>(
+    options: &[EngineOption],
+    name: K,
+    value: V,
+) -> Result<(), OptionValueError> {
+    match find_option(options, name.as_ref()) {
+        Some(option) => option.validate(value),
+        None => Err(OptionValueError::UnknownOptionError(
+            name.as_ref().to_string(),
+        )),
+    }
+}
+
+/// parse spin value
+fn parse_spin_value(
+    name: &str,
+    key: &'static str,
+    value: Option<&str>,
+) -> Result<i64, OptionParseError> {
+    match value {
+        Some(value) => match value.parse::<i64>() {
+            Ok(value) => Ok(value),
+            _ => Err(OptionParseError::InvalidValueError(
+                name.to_string(),
+                key,
+                value.to_string(),
+            )),
+        },
+        None => Err(OptionParseError::MissingValueError(name.to_string(), key)),
+    }
+}
+
+/// convert string value, mapping <empty> to an empty string
+fn string_value(value: Option<String>) -> String {
+    match value {
+        Some(value) if value != EMPTY_STRING => value,
+        _ => String::new(),
+    }
+}
+
+/// string value as sent by the engine, mapping an empty string to <empty>
+fn display_string_value(value: &str) -> &str {
+    match value {
+        "" => EMPTY_STRING,
+        _ => value,
+    }
+}
+
+#[test]
+fn parse_spin_and_combo() {
+    let option =
+        EngineOption::parse("option name Skill Level type spin default 20 min 0 max 20").unwrap();
+
+    assert_eq!(option.name, "Skill Level");
+    assert_eq!(
+        option.kind,
+        OptionKind::Spin {
+            default: 20,
+            min: 0,
+            max: 20
+        }
+    );
+
+    let option = EngineOption::parse(
+        "option name UCI_Variant type combo default chess var chess var atomic",
+    )
+    .unwrap();
+
+    assert_eq!(
+        option.kind,
+        OptionKind::Combo {
+            default: "chess".to_string(),
+            vars: vec!["chess".to_string(), "atomic".to_string()]
+        }
+    );
+}
+
+#[test]
+fn parse_string_check_and_button() {
+    let option = EngineOption::parse("option name SyzygyPath type string default <empty>").unwrap();
+
+    assert_eq!(
+        option.kind,
+        OptionKind::String {
+            default: String::new()
+        }
+    );
+
+    let option = EngineOption::parse("option name Ponder type check default false").unwrap();
+
+    assert_eq!(option.kind, OptionKind::Check { default: false });
+
+    let option = EngineOption::parse("option name Clear Hash type button").unwrap();
+
+    assert_eq!(option.name, "Clear Hash");
+    assert_eq!(option.kind, OptionKind::Button);
+
+    assert!(EngineOption::parse("option name Hash type spin default x min 1 max 2").is_err());
+
+    // a changed option is formatted as the engine would send it
+    let mut option = EngineOption::parse("option name Debug Log File type string default").unwrap();
+
+    assert_eq!(
+        option.to_string(),
+        "option name Debug Log File type string default"
+    );
+
+    option.kind = OptionKind::String {
+        default: "log.txt".to_string(),
+    };
+
+    assert_eq!(
+        option.to_string(),
+        "option name Debug Log File type string default log.txt"
+    );
+}
+
+#[test]
+fn validate_values() {
+    let options: Vec<EngineOption> = vec![
+        "option name Threads type spin default 1 min 1 max 512",
+        "option name UCI_Variant type combo default chess var chess var atomic",
+        "option name Ponder type check default false",
+    ]
+    .into_iter()
+    .map(|line| EngineOption::parse(line).unwrap())
+    .collect();
+
+    assert!(validate_option(&options, "threads", "4").is_ok());
+    assert!(validate_option(&options, "Threds", "4").is_err());
+    assert!(validate_option(&options, "Threads", "0").is_err());
+    assert!(validate_option(&options, "Threads", "many").is_err());
+    assert!(validate_option(&options, "UCI_Variant", "Atomic").is_ok());
+    assert!(validate_option(&options, "UCI_Variant", "shogi").is_err());
+    assert!(validate_option(&options, "Ponder", "TRUE").is_ok());
+    assert!(validate_option(&options, "Ponder", "yes").is_err());
+}
@@ -0,0 +1,274 @@
+use log::{debug, info, log_enabled, warn, Level};
+
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::mpsc;
+
+use crate::config::*;
+use crate::uciengine::*;
+
+/// pool of engine processes of the same binary with the same options,
+/// go jobs are dispatched to idle engines, so that as many searches run in parallel
+/// as there are engines, the other jobs wait in the order they were issued
+pub struct EnginePool {
+    engines: Vec<std::sync::Arc<UciEngine>>,
+    busy: std::sync::Arc<Vec<AtomicBool>>,
+    queued: AtomicUsize,
+    live: AtomicUsize,
+    quitting: AtomicBool,
+    idle_tx: mpsc::UnboundedSender<usize>,
+    idle_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<usize>>,
+}
+
+/// marks engine busy while a job runs on it and returns it to the idle engines when dropped
+struct IdleGuard {
+    index: usize,
+    busy: std::sync::Arc<Vec<AtomicBool>>,
+    idle_tx: mpsc::UnboundedSender<usize>,
+}
+
+/// implement Drop for IdleGuard
+impl Drop for IdleGuard {
+    fn drop(&mut self) {
+        self.busy[self.index].store(false, Ordering::Relaxed);
+
+        let _ = self.idle_tx.send(self.index);
+    }
+}
+
+/// counts a job waiting for an idle engine until dropped,
+/// so that a waiting job that is cancelled is no longer counted
+struct QueuedGuard<'a> {
+    queued: &'a AtomicUsize,
+}
+
+/// queued guard implementation
+impl<'a> QueuedGuard<'a> {
+    /// count waiting job
+    fn new(queued: &'a AtomicUsize) -> Self {
+        queued.fetch_add(1, Ordering::Relaxed);
+
+        Self { queued }
+    }
+}
+
+/// implement Drop for QueuedGuard
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// engine pool implementation
+impl EnginePool {
+    /// spawn size engines from config ( or path ), wait for their uci handshakes
+    /// and apply options to all of them,
+    /// if any engine fails to start, the engines already started are killed,
+    /// size has to be at least one
+    pub async fn try_new<C, I, K, V>(
+        config: C,
+        size: usize,
+        options: I,
+    ) -> Result<Self, EngineError>
+    where
+        C: Into<EngineConfig>,
+        I: IntoIterator<Item = (K, V)>,
+        K: core::fmt::Display,
+        V: core::fmt::Display,
+    {
+        if size == 0 {
+            return Err(EngineError::EmptyPoolError);
+        }
+
+        let config = config.into();
+
+        let options_job = options
+            .into_iter()
+            .fold(GoJob::new(), |go_job, (key, value)| {
+                go_job.uci_opt(key, value)
+            });
+
+        // start engines in parallel
+        let handles: Vec<_> = (0..size)
+            .map(|_| {
+                let config = config.clone();
+                let options_job = options_job.clone();
+
+                tokio::spawn(async move {
+                    let engine = UciEngine::try_with_config(config).await?;
+
+                    if let Err(err) = engine.check_ready(options_job).await {
+                        engine.kill();
+
+                        return Err(err);
+                    }
+
+                    Ok(engine)
+                })
+            })
+            .collect();
+
+        let mut engines: Vec<std::sync::Arc<UciEngine>> = Vec::with_capacity(size);
+        let mut first_err: Option<EngineError> = None;
+
+        for handle in handles {
+            let spawn_result = match handle.await {
+                Ok(spawn_result) => spawn_result,
+                Err(err) => Err(EngineError::HandshakeError(err.to_string())),
+            };
+
+            match spawn_result {
+                Ok(engine) => engines.push(engine),
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        if let Some(err) = first_err {
+            for engine in engines {
+                engine.kill();
+            }
+
+            return Err(err);
+        }
+
+        let (idle_tx, idle_rx) = mpsc::unbounded_channel::<usize>();
+
+        for index in 0..size {
+            let _ = idle_tx.send(index);
+        }
+
+        if log_enabled!(Level::Info) {
+            info!(
+                "started engine pool of {} engines : {}",
+                size,
+                config.path()
+            );
+        }
+
+        Ok(Self {
+            engines,
+            busy: std::sync::Arc::new((0..size).map(|_| AtomicBool::new(false)).collect()),
+            queued: AtomicUsize::new(0),
+            live: AtomicUsize::new(size),
+            quitting: AtomicBool::new(false),
+            idle_tx,
+            idle_rx: tokio::sync::Mutex::new(idle_rx),
+        })
+    }
+
+    /// number of engines
+    pub fn size(&self) -> usize {
+        self.engines.len()
+    }
+
+    /// get engine by index
+    pub fn engine(&self, index: usize) -> Option<std::sync::Arc<UciEngine>> {
+        self.engines.get(index).cloned()
+    }
+
+    /// number of jobs waiting for an idle engine
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// busy state of engines by index
+    pub fn busy(&self) -> Vec<bool> {
+        self.busy
+            .iter()
+            .map(|busy| busy.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// number of engines not running a job
+    pub fn idle_count(&self) -> usize {
+        self.busy().into_iter().filter(|busy| !busy).count()
+    }
+
+    /// wait for an idle engine that is alive and mark it busy,
+    /// engines that died leave the pool
+    async fn acquire(&self) -> Result<IdleGuard, EngineError> {
+        let queued = QueuedGuard::new(&self.queued);
+
+        // the mutex is fair, so jobs get engines in the order they were issued
+        let mut idle_rx = self.idle_rx.lock().await;
+
+        let index = loop {
+            if self.live.load(Ordering::Relaxed) == 0 {
+                return Err(EngineError::NoLiveEngineError);
+            }
+
+            let index = match idle_rx.recv().await {
+                Some(index) => index,
+                _ => return Err(EngineError::SendError),
+            };
+
+            if self.engines[index].is_alive() {
+                break index;
+            }
+
+            self.live.fetch_sub(1, Ordering::Relaxed);
+
+            if log_enabled!(Level::Warn) {
+                warn!("engine {} of pool died, no longer dispatching to it", index);
+            }
+        };
+
+        drop(idle_rx);
+        drop(queued);
+
+        let guard = IdleGuard {
+            index,
+            busy: self.busy.clone(),
+            idle_tx: self.idle_tx.clone(),
+        };
+
+        if self.quitting.load(Ordering::Relaxed) {
+            return Err(EngineError::SendError);
+        }
+
+        self.busy[index].store(true, Ordering::Relaxed);
+
+        Ok(guard)
+    }
+
+    /// issue go command on the next idle engine
+    pub async fn go(&self, go_job: GoJob) -> Result<GoResult, EngineError> {
+        if self.quitting.load(Ordering::Relaxed) {
+            return Err(EngineError::SendError);
+        }
+
+        let guard = self.acquire().await?;
+
+        if log_enabled!(Level::Debug) {
+            debug!("dispatching go job to engine {}", guard.index);
+        }
+
+        self.engines[guard.index].go(go_job).await
+    }
+
+    /// quit all engines in parallel, jobs issued after this fail,
+    /// returns the exit status of the engine processes by index
+    pub async fn quit(&self) -> Vec<Option<ExitStatus>> {
+        self.quitting.store(true, Ordering::Relaxed);
+
+        let handles: Vec<_> = self
+            .engines
+            .iter()
+            .map(|engine| {
+                let engine = engine.clone();
+
+                tokio::spawn(async move { engine.quit().await })
+            })
+            .collect();
+
+        let mut exit_statuses = Vec::with_capacity(handles.len());
+
+        for handle in handles {
+            exit_statuses.push(handle.await.unwrap_or(None));
+        }
+
+        exit_statuses
+    }
+}
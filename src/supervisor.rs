@@ -0,0 +1,214 @@
+use log::{error, info, log_enabled, warn, Level};
+
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+
+use crate::config::*;
+use crate::uciengine::*;
+
+/// default number of times a job is retried after the engine died
+const DEFAULT_MAX_RETRIES: usize = 3;
+
+/// default delay before the first retry, doubled for every further retry
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// event of the supervisor, for logging restarts
+#[derive(Debug, Clone)]
+pub enum SupervisorEvent {
+    /// engine process died
+    EngineDied { exit_status: Option<ExitStatus> },
+    /// engine was restarted, restarts is the number of restarts so far
+    Restarted { restarts: usize },
+    /// engine could not be restarted
+    RestartFailed { error: String },
+    /// job that failed is retried after delay
+    Retrying { attempt: usize, delay: Duration },
+}
+
+/// uci engine that is respawned when its process dies,
+/// options applied to the dead engine are applied to the new one
+/// and jobs that failed because the engine died are retried with backoff
+pub struct SupervisedEngine {
+    config: EngineConfig,
+    engine: tokio::sync::Mutex<std::sync::Arc<UciEngine>>,
+    restarts: AtomicUsize,
+    quitting: AtomicBool,
+    max_retries: usize,
+    backoff: Duration,
+    etx: broadcast::Sender<SupervisorEvent>,
+}
+
+/// supervised engine implementation
+impl SupervisedEngine {
+    /// spawn engine from config ( or path ) and wait for the uci handshake to complete
+    pub async fn try_new<C>(config: C) -> Result<Self, EngineError>
+    where
+        C: Into<EngineConfig>,
+    {
+        let config = config.into();
+
+        let engine = UciEngine::try_with_config(config.clone()).await?;
+
+        let (etx, _) = broadcast::channel::<SupervisorEvent>(20);
+
+        Ok(Self {
+            config,
+            engine: tokio::sync::Mutex::new(engine),
+            restarts: AtomicUsize::new(0),
+            quitting: AtomicBool::new(false),
+            max_retries: DEFAULT_MAX_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            etx,
+        })
+    }
+
+    /// set number of times a job is retried after the engine died and return self
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+
+        self
+    }
+
+    /// set delay before the first retry and return self,
+    /// the delay is doubled for every further retry
+    #[must_use]
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+
+        self
+    }
+
+    /// subscribe to supervisor events
+    pub fn subscribe(&self) -> broadcast::Receiver<SupervisorEvent> {
+        self.etx.subscribe()
+    }
+
+    /// number of times the engine was restarted
+    pub fn restarts(&self) -> usize {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// emit supervisor event
+    fn emit(&self, event: SupervisorEvent) {
+        // having no subscribers is not an error
+        let _ = self.etx.send(event);
+    }
+
+    /// get engine, restarting it if its process died or it is unhealthy,
+    /// use it for jobs that should not be retried, like analysis streams
+    pub async fn engine(&self) -> Result<std::sync::Arc<UciEngine>, EngineError> {
+        let mut engine = self.engine.lock().await;
+
+        if self.quitting.load(Ordering::Relaxed) {
+            return Ok(engine.clone());
+        }
+
+        // an unhealthy engine is being killed, it is replaced without waiting for its exit
+        let exit_status = match engine.state() {
+            EngineState::Alive => return Ok(engine.clone()),
+            EngineState::Unhealthy => None,
+            EngineState::Dead { exit_status } => exit_status,
+        };
+
+        if log_enabled!(Level::Warn) {
+            warn!("engine died ( exit status {:?} ), restarting", exit_status);
+        }
+
+        self.emit(SupervisorEvent::EngineDied { exit_status });
+
+        match self.respawn(engine.applied_options()).await {
+            Ok(new_engine) => {
+                *engine = new_engine;
+
+                let restarts = self.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+
+                if log_enabled!(Level::Info) {
+                    info!("engine restarted ( restarts {} )", restarts);
+                }
+
+                self.emit(SupervisorEvent::Restarted { restarts });
+
+                Ok(engine.clone())
+            }
+            Err(err) => {
+                if log_enabled!(Level::Error) {
+                    error!("engine restart failed : {}", err);
+                }
+
+                self.emit(SupervisorEvent::RestartFailed {
+                    error: err.to_string(),
+                });
+
+                Err(err)
+            }
+        }
+    }
+
+    /// spawn new engine and apply options
+    async fn respawn(
+        &self,
+        options: std::collections::HashMap<String, String>,
+    ) -> Result<std::sync::Arc<UciEngine>, EngineError> {
+        let engine = UciEngine::try_with_config(self.config.clone()).await?;
+
+        let go_job = options
+            .into_iter()
+            .fold(GoJob::new(), |go_job, (key, value)| {
+                go_job.uci_opt(key, value)
+            });
+
+        engine.check_ready(go_job).await?;
+
+        Ok(engine)
+    }
+
+    /// issue go command, if the engine dies the engine is restarted
+    /// and the job is retried up to max retries times
+    pub async fn go(&self, go_job: GoJob) -> Result<GoResult, EngineError> {
+        let mut attempt: usize = 0;
+
+        loop {
+            let go_result = match self.engine().await {
+                Ok(engine) => engine.go(go_job.clone()).await,
+                Err(err) => Err(err),
+            };
+
+            let retry = match &go_result {
+                Err(EngineError::EngineDied { .. })
+                | Err(EngineError::UnhealthyError)
+                | Err(EngineError::HandshakeError(_)) => {
+                    (attempt < self.max_retries) && (!self.quitting.load(Ordering::Relaxed))
+                }
+                _ => false,
+            };
+
+            if !retry {
+                return go_result;
+            }
+
+            let delay = self
+                .backoff
+                .saturating_mul(2u32.saturating_pow(attempt as u32));
+
+            attempt += 1;
+
+            if log_enabled!(Level::Warn) {
+                warn!("retrying job ( attempt {} ) in {:?}", attempt, delay);
+            }
+
+            self.emit(SupervisorEvent::Retrying { attempt, delay });
+
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// quit engine, it is not restarted any more, returns the exit status of the engine process
+    pub async fn quit(&self) -> Option<ExitStatus> {
+        self.quitting.store(true, Ordering::Relaxed);
+
+        self.engine.lock().await.quit().await
+    }
+}
@@ -0,0 +1,248 @@
+use log::{error, log_enabled, Level};
+
+use thiserror::Error;
+
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// TranscriptParseError captures possible transcript line parsing errors
+#[derive(Error, Debug)]
+pub enum TranscriptParseError {
+    #[error("transcript line '{0}' has no valid timestamp")]
+    InvalidTimestampError(String),
+    #[error("transcript line '{0}' has no valid direction")]
+    InvalidDirectionError(String),
+}
+
+/// direction of a transcript line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// command sent to the engine, tagged ">"
+    ToEngine,
+    /// line sent by the engine, tagged "<"
+    FromEngine,
+}
+
+/// direction implementation
+impl Direction {
+    /// tag of direction in transcript lines
+    pub fn tag(self) -> &'static str {
+        match self {
+            Direction::ToEngine => ">",
+            Direction::FromEngine => "<",
+        }
+    }
+}
+
+/// transcript line, formatted as "<milliseconds since engine start> <direction tag> <line>"
+/// ( e.g. "1520 > go depth 20" or "3100 < bestmove e2e4" )
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptLine {
+    /// milliseconds since the engine was started
+    pub millis: u64,
+    /// direction
+    pub direction: Direction,
+    /// protocol line
+    pub line: String,
+}
+
+/// transcript line implementation
+impl TranscriptLine {
+    /// parse transcript line
+    pub fn parse<T: AsRef<str>>(line: T) -> Result<Self, TranscriptParseError> {
+        let line = line.as_ref();
+
+        let mut parts = line.splitn(3, ' ');
+
+        let millis = match parts.next().map(|millis| millis.parse::<u64>()) {
+            Some(Ok(millis)) => millis,
+            _ => {
+                return Err(TranscriptParseError::InvalidTimestampError(
+                    line.to_string(),
+                ))
+            }
+        };
+
+        let direction = match parts.next() {
+            Some(">") => Direction::ToEngine,
+            Some("<") => Direction::FromEngine,
+            _ => {
+                return Err(TranscriptParseError::InvalidDirectionError(
+                    line.to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            millis,
+            direction,
+            line: parts.next().unwrap_or("").to_string(),
+        })
+    }
+}
+
+/// implement Display for TranscriptLine
+impl std::fmt::Display for TranscriptLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.millis, self.direction.tag(), self.line)
+    }
+}
+
+/// parse transcript, skipping empty lines
+pub fn parse_transcript<T: AsRef<str>>(
+    transcript: T,
+) -> Result<Vec<TranscriptLine>, TranscriptParseError> {
+    transcript
+        .as_ref()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(TranscriptLine::parse)
+        .collect()
+}
+
+/// where the transcript is recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptTarget {
+    /// append lines to file
+    File(PathBuf),
+    /// keep the last lines in memory, up to capacity
+    Ring(usize),
+}
+
+/// transcript sink
+#[derive(Debug)]
+enum TranscriptSink {
+    /// lines are written by a task, so that recording does not block on the disk
+    File(mpsc::UnboundedSender<TranscriptLine>),
+    Ring {
+        capacity: usize,
+        lines: VecDeque<TranscriptLine>,
+    },
+}
+
+/// protocol transcript of an engine
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    start: Instant,
+    sink: std::sync::Arc<std::sync::Mutex<TranscriptSink>>,
+}
+
+/// transcript implementation
+impl Transcript {
+    /// open transcript for target, the file of a file target is created or appended to,
+    /// a file transcript has to be opened within a tokio runtime
+    pub fn open(target: &TranscriptTarget) -> std::io::Result<Self> {
+        let sink = match target {
+            TranscriptTarget::File(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+                TranscriptSink::File(spawn_writer(tokio::fs::File::from_std(file)))
+            }
+            TranscriptTarget::Ring(capacity) => TranscriptSink::Ring {
+                capacity: *capacity,
+                lines: VecDeque::new(),
+            },
+        };
+
+        Ok(Self {
+            start: Instant::now(),
+            sink: std::sync::Arc::new(std::sync::Mutex::new(sink)),
+        })
+    }
+
+    /// record line
+    pub fn record<T: AsRef<str>>(&self, direction: Direction, line: T) {
+        let transcript_line = TranscriptLine {
+            millis: self.start.elapsed().as_millis() as u64,
+            direction,
+            line: line.as_ref().to_string(),
+        };
+
+        match &mut *self.sink.lock().unwrap() {
+            TranscriptSink::File(tx) => {
+                // the writer only stops when the file cannot be written
+                let _ = tx.send(transcript_line);
+            }
+            TranscriptSink::Ring { capacity, lines } => {
+                if *capacity == 0 {
+                    return;
+                }
+
+                if lines.len() >= *capacity {
+                    lines.pop_front();
+                }
+
+                lines.push_back(transcript_line);
+            }
+        }
+    }
+
+    /// get recorded lines, oldest first, always empty for a file transcript
+    pub fn lines(&self) -> Vec<TranscriptLine> {
+        match &*self.sink.lock().unwrap() {
+            TranscriptSink::Ring { lines, .. } => lines.iter().cloned().collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// spawn task writing transcript lines to file, one write per line,
+/// returns the sender of lines to write
+fn spawn_writer(mut file: tokio::fs::File) -> mpsc::UnboundedSender<TranscriptLine> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<TranscriptLine>();
+
+    tokio::spawn(async move {
+        while let Some(transcript_line) = rx.recv().await {
+            let line = format!("{}\n", transcript_line);
+
+            let write_result = match file.write_all(line.as_bytes()).await {
+                Ok(()) => file.flush().await,
+                Err(err) => Err(err),
+            };
+
+            if let Err(err) = write_result {
+                if log_enabled!(Level::Error) {
+                    error!("could not write transcript : {}", err);
+                }
+
+                return;
+            }
+        }
+    });
+
+    tx
+}
+
+#[test]
+fn ring_and_parse() {
+    let transcript = Transcript::open(&TranscriptTarget::Ring(2)).unwrap();
+
+    transcript.record(Direction::ToEngine, "go depth 1");
+    transcript.record(Direction::FromEngine, "info depth 1 pv e2e4");
+    transcript.record(Direction::FromEngine, "bestmove e2e4");
+
+    let lines = transcript.lines();
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].line, "bestmove e2e4");
+
+    let text: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+
+    assert_eq!(parse_transcript(text).unwrap(), lines);
+
+    assert_eq!(
+        TranscriptLine::parse("12 > position startpos moves e2e4").unwrap(),
+        TranscriptLine {
+            millis: 12,
+            direction: Direction::ToEngine,
+            line: "position startpos moves e2e4".to_string()
+        }
+    );
+
+    assert!(TranscriptLine::parse("x > uci").is_err());
+    assert!(TranscriptLine::parse("12 = uci").is_err());
+}
@@ -1,17 +1,82 @@
-use log::{debug, error, info, log_enabled, Level};
+use log::{debug, error, info, log_enabled, warn, Level};
 
-use envor::envor::env_true;
+use thiserror::Error;
 
-use std::collections::HashMap;
-use std::process::Stdio;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::task::{Context, Poll};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::Command;
+use tokio::process::ChildStdin;
 use tokio::sync::*;
+use tokio_stream::Stream;
 
 use crate::analysis::*;
+use crate::config::*;
+use crate::message::*;
+use crate::options::*;
+use crate::transcript::*;
+use crate::ucimove::*;
+
+/// time allowed for the engine to answer the handshake
+const HANDSHAKE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_millis(10000);
+
+/// time allowed for the engine to report bestmove after being stopped at the deadline of a job
+const STOP_GRACE_PERIOD: tokio::time::Duration = tokio::time::Duration::from_millis(2000);
+
+/// number of captured stderr lines kept in the stderr log of the engine
+const STDERR_LOG_SIZE: usize = 100;
+
+/// time allowed for the engine process to exit after quit, before it is killed
+const QUIT_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_millis(3000);
+
+/// time allowed for the engine process to exit after closing its output,
+/// before it is killed
+const EXIT_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_millis(1000);
+
+/// EngineError captures possible engine errors
+#[derive(Error, Debug)]
+pub enum EngineError {
+    #[error("could not spawn engine '{0}' : {1}")]
+    SpawnError(String, std::io::Error),
+    #[error("engine process did not have a handle to {0}")]
+    MissingPipeError(&'static str),
+    #[error("engine handshake failed : {0}")]
+    HandshakeError(String),
+    #[error("engine is no longer accepting jobs")]
+    SendError,
+    #[error("engine terminated before sending a result")]
+    RecvError,
+    #[error("invalid uci option : {0}")]
+    InvalidOptionError(#[from] OptionValueError),
+    #[error("invalid position moves : {0}")]
+    InvalidMoveError(#[from] UciMoveParseError),
+    #[error("engine died ( exit status {exit_status:?} )")]
+    EngineDied { exit_status: Option<ExitStatus> },
+    #[error("engine is unhealthy")]
+    UnhealthyError,
+    #[error("could not open transcript : {0}")]
+    TranscriptError(std::io::Error),
+    #[error("engine pool needs at least one engine")]
+    EmptyPoolError,
+    #[error("no engine of the pool is alive")]
+    NoLiveEngineError,
+}
+
+/// state of the engine process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineState {
+    /// engine process is running
+    Alive,
+    /// engine stopped responding and is being killed
+    Unhealthy,
+    /// engine process exited, exit status is None if it could not be obtained
+    Dead { exit_status: Option<ExitStatus> },
+}
 
 /// enum of possible position specifiers
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum PosSpec {
     /// starting position
     Startpos,
@@ -23,19 +88,33 @@ pub enum PosSpec {
 
 use PosSpec::*;
 
+/// job priority, jobs of higher priority are dispatched first,
+/// jobs of the same priority are dispatched in the order they were issued
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JobPriority {
+    /// time critical jobs, like game moves
+    Realtime,
+    /// default priority
+    Normal,
+    /// jobs that can wait, like background analysis
+    Background,
+}
+
 /// go command job
 #[derive(Debug)]
 pub struct GoJob {
-    /// uci options as key value pairs
-    uci_options: HashMap<String, String>,
+    /// uci options as key value pairs, ordered so that commands are the same in every process
+    uci_options: BTreeMap<String, String>,
     /// position specifier
     pos_spec: PosSpec,
     /// position fen
     pos_fen: Option<String>,
     /// position moves
-    pos_moves: Option<String>,
-    /// go command options as key value pairs
-    go_options: HashMap<String, String>,
+    pos_moves: Vec<UciMove>,
+    /// error of parsing position moves, reported when the job is issued
+    pos_moves_error: Option<UciMoveParseError>,
+    /// go command options as key value pairs, ordered so that commands are the same in every process
+    go_options: BTreeMap<String, String>,
     /// custom command
     custom_command: Option<String>,
     /// ponder ( go option )
@@ -44,11 +123,33 @@ pub struct GoJob {
     ponderhit: bool,
     /// pondermiss ( alias to awaited stop )
     pondermiss: bool,
+    /// infinite ( go option )
+    infinite: bool,
+    /// time allowed for the search before it is stopped
+    deadline: Option<tokio::time::Duration>,
+    /// priority
+    priority: JobPriority,
+    /// stop a running background search to run this job
+    preempt: bool,
     /// result sender
-    rtx: Option<oneshot::Sender<GoResult>>,
+    rtx: Option<oneshot::Sender<Result<GoResult, EngineError>>>,
+    /// analysis event sender for the search started by this job
+    info_tx: Option<mpsc::UnboundedSender<AnalysisEvent>>,
+    /// stop signal for the search started by this job
+    stop_rx: Option<oneshot::Receiver<()>>,
     should_go: bool,
 }
 
+/// setoption command for option key and value
+fn setoption_command(key: &str, value: &str) -> String {
+    format!("setoption name {} value {}", key, value)
+}
+
+/// setoption command for button, buttons have no value
+fn button_command(key: &str) -> String {
+    format!("setoption name {}", key)
+}
+
 /// time control ( all values are in milliseconds )
 #[derive(Debug)]
 pub struct Timecontrol {
@@ -76,6 +177,33 @@ impl Timecontrol {
     }
 }
 
+/// implement Clone for GoJob, the clone is not bound to the result,
+/// analysis stream or stop signal of the original job
+impl Clone for GoJob {
+    fn clone(&self) -> Self {
+        Self {
+            uci_options: self.uci_options.clone(),
+            pos_spec: self.pos_spec.clone(),
+            pos_fen: self.pos_fen.clone(),
+            pos_moves: self.pos_moves.clone(),
+            pos_moves_error: self.pos_moves_error.clone(),
+            go_options: self.go_options.clone(),
+            custom_command: self.custom_command.clone(),
+            ponder: self.ponder,
+            ponderhit: self.ponderhit,
+            pondermiss: self.pondermiss,
+            infinite: self.infinite,
+            deadline: self.deadline,
+            priority: self.priority,
+            preempt: self.preempt,
+            rtx: None,
+            info_tx: None,
+            stop_rx: None,
+            should_go: self.should_go,
+        }
+    }
+}
+
 /// go command job implementation
 impl GoJob {
     /// create new GoJob with defaults
@@ -83,14 +211,21 @@ impl GoJob {
         Self {
             pos_spec: No,
             pos_fen: None,
-            pos_moves: None,
-            uci_options: HashMap::new(),
-            go_options: HashMap::new(),
+            pos_moves: vec![],
+            pos_moves_error: None,
+            uci_options: BTreeMap::new(),
+            go_options: BTreeMap::new(),
             rtx: None,
+            info_tx: None,
             custom_command: None,
             ponder: false,
             ponderhit: false,
             pondermiss: false,
+            infinite: false,
+            deadline: None,
+            priority: JobPriority::Normal,
+            preempt: false,
+            stop_rx: None,
             should_go: false,
         }
     }
@@ -108,35 +243,83 @@ impl GoJob {
         self
     }
 
-    /// convert go job to commands
-    pub fn to_commands(&self) -> Vec<String> {
-        let mut commands: Vec<String> = vec![];
+    /// check that the job can be issued to an engine declaring options
+    fn validate(&mut self, options: &[EngineOption]) -> Result<(), EngineError> {
+        if let Some(err) = self.pos_moves_error.take() {
+            return Err(err.into());
+        }
 
-        if self.ponderhit {
-            commands.push("ponderhit".to_string());
-            return commands;
+        for (key, value) in &self.uci_options {
+            validate_option(options, key, value)?;
         }
 
-        if self.pondermiss {
-            commands.push("stop".to_string());
+        Ok(())
+    }
+
+    /// true if the job starts a search that awaits bestmove,
+    /// only such a job is skipped or stopped when its result receiver is dropped
+    fn is_cancellable(&self) -> bool {
+        self.control_command().is_none() && self.should_go && (!self.ponder)
+    }
 
-            return commands;
+    /// true if the job may stop a running search of priority
+    fn preempts(&self, priority: JobPriority) -> bool {
+        self.preempt
+            && (self.priority == JobPriority::Realtime)
+            && (priority == JobPriority::Background)
+    }
+
+    /// true if the result receiver of the job was dropped
+    fn is_abandoned(&self) -> bool {
+        matches!(&self.rtx, Some(rtx) if rtx.is_closed())
+    }
+
+    /// send error as the result of the job
+    fn fail(self, err: EngineError) {
+        if let Some(rtx) = self.rtx {
+            let _ = rtx.send(Err(err));
         }
+    }
+
+    /// convert go job to commands
+    pub fn to_commands(&self) -> Vec<String> {
+        if let Some(command) = self.control_command() {
+            return vec![command];
+        }
+
+        let mut commands: Vec<String> = self
+            .uci_options
+            .iter()
+            .map(|(key, value)| setoption_command(key, value))
+            .collect();
 
-        if let Some(command) = &self.custom_command {
-            commands.push(command.to_string());
+        commands.append(&mut self.search_commands());
 
-            return commands;
+        commands
+    }
+
+    /// command of a ponderhit, pondermiss or custom job,
+    /// these jobs consist of this single command
+    fn control_command(&self) -> Option<String> {
+        if self.ponderhit {
+            return Some("ponderhit".to_string());
         }
 
-        for (key, value) in &self.uci_options {
-            commands.push(format!("setoption name {} value {}", key, value));
+        if self.pondermiss {
+            return Some("stop".to_string());
         }
 
+        self.custom_command.clone()
+    }
+
+    /// position and go commands ( isready if there is no go )
+    fn search_commands(&self) -> Vec<String> {
+        let mut commands: Vec<String> = vec![];
+
         let mut pos_command_moves = "".to_string();
 
-        if let Some(pos_moves) = &self.pos_moves {
-            pos_command_moves = format!(" moves {}", pos_moves)
+        if !self.pos_moves.is_empty() {
+            pos_command_moves = format!(" moves {}", moves_to_string(&self.pos_moves))
         }
 
         let pos_command: Option<String> = match self.pos_spec {
@@ -166,6 +349,10 @@ impl GoJob {
                 go_command = go_command + &format!(" {}", "ponder");
             }
 
+            if self.infinite {
+                go_command = go_command + &format!(" {}", "infinite");
+            }
+
             commands.push(go_command);
         } else {
             commands.push("isready".to_string());
@@ -190,6 +377,43 @@ impl GoJob {
         self
     }
 
+    /// set infinite and return self,
+    /// the search only ends when it is stopped
+    #[must_use]
+    pub fn infinite(mut self) -> Self {
+        self.should_go = true;
+        self.infinite = true;
+
+        self
+    }
+
+    /// set deadline and return self, the search is stopped when the deadline passes,
+    /// if the engine does not report bestmove within a grace period after that,
+    /// it is killed and the job ends with GoOutcome::Timeout
+    #[must_use]
+    pub fn deadline(mut self, deadline: tokio::time::Duration) -> Self {
+        self.deadline = Some(deadline);
+
+        self
+    }
+
+    /// set priority and return self
+    #[must_use]
+    pub fn priority(mut self, priority: JobPriority) -> Self {
+        self.priority = priority;
+
+        self
+    }
+
+    /// set preempt and return self, a realtime job with preempt set
+    /// stops a running background search, which ends with GoOutcome::Preempted
+    #[must_use]
+    pub fn preempt(mut self) -> Self {
+        self.preempt = true;
+
+        self
+    }
+
     /// set ponderhit and return self
     #[must_use]
     pub fn ponderhit(mut self) -> Self {
@@ -243,7 +467,22 @@ impl GoJob {
     where
         T: core::fmt::Display,
     {
-        self.pos_moves = Some(format!("{}", moves));
+        match UciMove::parse_list(format!("{}", moves)) {
+            Ok(moves) => self.pos_moves = moves,
+            Err(err) => self.pos_moves_error = Some(err),
+        }
+
+        self
+    }
+
+    /// set position moves from list of moves and return self
+    #[must_use]
+    pub fn pos_move_list<I>(mut self, moves: I) -> Self
+    where
+        I: IntoIterator<Item = UciMove>,
+    {
+        self.pos_moves = moves.into_iter().collect();
+        self.pos_moves_error = None;
 
         self
     }
@@ -278,6 +517,7 @@ impl GoJob {
     /// set time control and return self
     #[must_use]
     pub fn tc(mut self, tc: Timecontrol) -> Self {
+        self.should_go = true;
         self.go_options
             .insert("wtime".to_string(), format!("{}", tc.wtime));
         self.go_options
@@ -291,76 +531,580 @@ impl GoJob {
     }
 }
 
+/// how a job ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoOutcome {
+    /// engine reported a best move
+    BestMove,
+    /// position has no legal move ( mate or stalemate ), engine sent bestmove (none) or 0000
+    NoLegalMove,
+    /// engine sent bestmove without a usable move
+    Aborted,
+    /// search was stopped to run a realtime job, bestmove is the best move found so far
+    Preempted,
+    /// engine did not report bestmove within the grace period after the deadline of the job,
+    /// the engine was killed
+    Timeout,
+    /// job did not search, engine answered isready
+    Ready,
+}
+
 /// go command result
 #[derive(Debug)]
 pub struct GoResult {
-    /// best move if any
-    pub bestmove: Option<String>,
+    /// how the job ended
+    pub outcome: GoOutcome,
+    /// best move, set for GoOutcome::BestMove and for a preempted search that found one
+    pub bestmove: Option<UciMove>,
     /// ponder if any
-    pub ponder: Option<String>,
+    pub ponder: Option<UciMove>,
+    /// win / draw / loss of the last score, if the engine reports it ( UCI_ShowWDL )
+    pub wdl: Option<Wdl>,
     /// analysis info
     pub ai: AnalysisInfo,
+    /// analysis lines by multipv index
+    pub multipv: MultiPvSnapshot,
+    /// info strings sent by the engine during the job
+    pub strings: Vec<String>,
     pub is_ready: bool,
 }
 
+/// go result implementation
+impl GoResult {
+    /// create go result from the bestmove or readyok message that ended the job
+    fn from_message(
+        message: Result<EngineMessage, MessageParseError>,
+        ai: AnalysisInfo,
+        multipv: MultiPvSnapshot,
+    ) -> Self {
+        let mut go_result = Self::with_outcome(GoOutcome::Aborted, ai, multipv);
+
+        match message {
+            Ok(EngineMessage::ReadyOk) => {
+                go_result.outcome = GoOutcome::Ready;
+                go_result.is_ready = true;
+            }
+            Ok(EngineMessage::BestMove {
+                bestmove: None | Some(UciMove::Null),
+                ..
+            }) => go_result.outcome = GoOutcome::NoLegalMove,
+            Ok(EngineMessage::BestMove {
+                bestmove: Some(bestmove),
+                ponder,
+            }) => {
+                go_result.outcome = GoOutcome::BestMove;
+                go_result.bestmove = Some(bestmove);
+                go_result.ponder = ponder.filter(|ponder| !ponder.is_null());
+            }
+            Ok(message) => {
+                if log_enabled!(Level::Warn) {
+                    warn!("job ended on unexpected message {}", message);
+                }
+            }
+            Err(err) => {
+                if log_enabled!(Level::Warn) {
+                    warn!("{}", err);
+                }
+            }
+        }
+
+        go_result
+    }
+
+    /// create go result without moves
+    fn with_outcome(outcome: GoOutcome, ai: AnalysisInfo, multipv: MultiPvSnapshot) -> Self {
+        Self {
+            outcome,
+            bestmove: None,
+            ponder: None,
+            wdl: ai.wdl,
+            ai,
+            multipv,
+            strings: vec![],
+            is_ready: false,
+        }
+    }
+
+    /// true if the engine reported a move to play
+    pub fn has_move(&self) -> bool {
+        self.outcome == GoOutcome::BestMove
+    }
+}
+
+/// pending result of a job issued to the engine,
+/// resolves to the go result or the error that prevented it
+#[derive(Debug)]
+pub struct GoHandle {
+    rrx: Result<oneshot::Receiver<Result<GoResult, EngineError>>, Option<EngineError>>,
+    /// engine, kept running as long as the handle exists
+    _engine: Option<std::sync::Arc<UciEngine>>,
+}
+
+/// go handle implementation
+impl GoHandle {
+    /// create go handle that resolves to an error
+    fn failed(err: EngineError) -> Self {
+        Self {
+            rrx: Err(Some(err)),
+            _engine: None,
+        }
+    }
+}
+
+/// implement Future for GoHandle
+impl Future for GoHandle {
+    type Output = Result<GoResult, EngineError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.rrx {
+            Ok(rrx) => Pin::new(rrx)
+                .poll(cx)
+                .map(|recv_result| recv_result.unwrap_or(Err(EngineError::RecvError))),
+            Err(err) => Poll::Ready(Err(err.take().unwrap_or(EngineError::RecvError))),
+        }
+    }
+}
+
+/// stream of analysis infos and info strings of a single search,
+/// ends when the engine reports bestmove
+#[derive(Debug)]
+pub struct AnalysisStream {
+    irx: mpsc::UnboundedReceiver<AnalysisEvent>,
+    go_handle: GoHandle,
+    snapshot: MultiPvSnapshot,
+}
+
+/// analysis stream implementation
+impl AnalysisStream {
+    /// get analysis lines by multipv index, as streamed so far
+    pub fn snapshot(&self) -> &MultiPvSnapshot {
+        &self.snapshot
+    }
+
+    /// wait for the go result of the search
+    pub async fn result(self) -> Result<GoResult, EngineError> {
+        self.go_handle.await
+    }
+}
+
+/// implement Stream for AnalysisStream
+impl Stream for AnalysisStream {
+    type Item = AnalysisEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.irx.poll_recv(cx);
+
+        if let Poll::Ready(Some(AnalysisEvent::Info(ai))) = &poll {
+            self.snapshot.update(ai);
+        }
+
+        poll
+    }
+}
+
+/// infinite analysis of a position, streams the analysis events of the search
+/// until it is stopped, dropping the session stops the search
+#[derive(Debug)]
+pub struct AnalysisSession {
+    engine: std::sync::Arc<UciEngine>,
+    stream: AnalysisStream,
+    stop_tx: Option<oneshot::Sender<()>>,
+}
+
+/// analysis session implementation
+impl AnalysisSession {
+    /// get analysis lines by multipv index, as streamed so far
+    pub fn snapshot(&self) -> &MultiPvSnapshot {
+        self.stream.snapshot()
+    }
+
+    /// stop the search and wait for its go result
+    pub async fn stop(mut self) -> Result<GoResult, EngineError> {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+
+        self.stream.go_handle.await
+    }
+
+    /// stop the search and start an infinite search of go job in its place,
+    /// returns the go result of the stopped search, its bestmove is consumed
+    /// by the stopped search and will not be mistaken for the result of another job
+    pub async fn retarget(&mut self, go_job: GoJob) -> Result<GoResult, EngineError> {
+        let next = self.engine.start_infinite(go_job);
+
+        std::mem::replace(self, next).stop().await
+    }
+}
+
+/// implement Stream for AnalysisSession
+impl Stream for AnalysisSession {
+    type Item = AnalysisEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+}
+
+/// jobs waiting to be dispatched, by priority
+#[derive(Debug, Default)]
+struct JobLanes {
+    lanes: BTreeMap<JobPriority, VecDeque<GoJob>>,
+}
+
+/// job lanes implementation
+impl JobLanes {
+    /// add job to the lane of its priority
+    fn push(&mut self, go_job: GoJob) {
+        self.lanes
+            .entry(go_job.priority)
+            .or_default()
+            .push_back(go_job);
+    }
+
+    /// take first job of the highest priority lane
+    fn pop(&mut self) -> Option<GoJob> {
+        self.lanes.values_mut().find_map(|lane| lane.pop_front())
+    }
+}
+
+/// sending end of the engine job queue
+#[derive(Debug, Clone)]
+struct JobQueue {
+    gtx: mpsc::UnboundedSender<GoJob>,
+    state_rx: watch::Receiver<EngineState>,
+}
+
+/// job queue implementation
+impl JobQueue {
+    /// queue job for the engine task
+    fn send(&self, go_job: GoJob) -> Result<(), EngineError> {
+        match *self.state_rx.borrow() {
+            EngineState::Alive => {}
+            EngineState::Unhealthy => return Err(EngineError::UnhealthyError),
+            EngineState::Dead { exit_status } => {
+                return Err(EngineError::EngineDied { exit_status })
+            }
+        }
+
+        let send_result = self.gtx.send(go_job);
+
+        if log_enabled!(Level::Debug) {
+            debug!("send go job result {:?}", send_result);
+        }
+
+        send_result.map_err(|_| EngineError::SendError)
+    }
+}
+
+/// wait for stop signal, never resolves if there is none
+async fn stop_requested(stop_rx: &mut Option<oneshot::Receiver<()>>) {
+    match stop_rx {
+        // a dropped sender also means stop, nobody is left to stop the search
+        Some(stop_rx) => {
+            let _ = stop_rx.await;
+        }
+        _ => std::future::pending::<()>().await,
+    }
+}
+
+/// wait for the result receiver to be dropped, never resolves if there is no result sender
+async fn result_dropped(rtx: &mut Option<oneshot::Sender<Result<GoResult, EngineError>>>) {
+    match rtx {
+        Some(rtx) => rtx.closed().await,
+        _ => std::future::pending::<()>().await,
+    }
+}
+
+/// wait for deadline, never resolves if there is none
+async fn deadline_passed(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        _ => std::future::pending::<()>().await,
+    }
+}
+
+/// wait until the engine process has exited and return its exit status
+async fn engine_died(state_rx: &mut watch::Receiver<EngineState>) -> Option<ExitStatus> {
+    loop {
+        if let EngineState::Dead { exit_status } = *state_rx.borrow() {
+            return exit_status;
+        }
+
+        if state_rx.changed().await.is_err() {
+            return None;
+        }
+    }
+}
+
+/// engine identity as reported by id commands
+#[derive(Debug, Clone, Default)]
+pub struct EngineId {
+    /// engine name
+    pub name: Option<String>,
+    /// engine author
+    pub author: Option<String>,
+}
+
 /// uci engine
 pub struct UciEngine {
-    gtx: mpsc::UnboundedSender<GoJob>,
+    queue: JobQueue,
     pub ai: std::sync::Arc<std::sync::Mutex<AnalysisInfo>>,
     pub atx: std::sync::Arc<broadcast::Sender<AnalysisInfo>>,
+    stderr_tx: broadcast::Sender<String>,
+    stderr_log: std::sync::Arc<std::sync::Mutex<VecDeque<String>>>,
+    transcript: Option<Transcript>,
+    id: std::sync::Arc<std::sync::Mutex<EngineId>>,
+    options: std::sync::Arc<std::sync::Mutex<Vec<EngineOption>>>,
+    resend_options: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    applied_options: std::sync::Arc<std::sync::Mutex<HashMap<String, (String, String)>>>,
+    qtx: mpsc::UnboundedSender<()>,
+    ktx: mpsc::UnboundedSender<()>,
+}
+
+/// implement Debug for UciEngine
+impl std::fmt::Debug for UciEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UciEngine")
+            .field("id", &self.id())
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+/// implement Drop for UciEngine, kills the engine process if it is still running,
+/// go handles, analysis streams and sessions hold the engine, so it is only dropped
+/// when they are gone too
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        if self.state() != EngineState::Alive {
+            return;
+        }
+
+        if log_enabled!(Level::Debug) {
+            debug!("engine dropped, killing engine process");
+        }
+
+        self.kill();
+    }
+}
+
+/// engine stdin, with the transcript commands are recorded to
+struct EngineStdin {
+    stdin: ChildStdin,
+    transcript: Option<Transcript>,
+}
+
+/// write command to engine stdin
+async fn write_command(stdin: &mut EngineStdin, command: &str) -> std::io::Result<()> {
+    if let Some(transcript) = &stdin.transcript {
+        transcript.record(Direction::ToEngine, command);
+    }
+
+    let command = format!("{}\n", command);
+
+    if log_enabled!(Level::Debug) {
+        debug!("issuing engine command : {}", command);
+    }
+
+    let write_result = stdin.stdin.write_all(command.as_bytes()).await;
+
+    if log_enabled!(Level::Debug) {
+        debug!("write result {:?}", write_result);
+    }
+
+    write_result
 }
 
 /// uci engine implementation
 impl UciEngine {
-    /// create new uci engine
+    /// create new uci engine,
+    /// panics if the engine process cannot be spawned,
+    /// use try_new to handle spawn and handshake errors
     pub fn new<T>(path: T) -> std::sync::Arc<UciEngine>
     where
         T: core::fmt::Display,
     {
-        // you can use anything that can be converted to string as path
-        let path = path.to_string();
+        match Self::spawn(EngineConfig::new(path)) {
+            Ok((engine, _)) => engine,
+            Err(err) => panic!("failed to spawn engine : {}", err),
+        }
+    }
+
+    /// create new uci engine and wait for the uci handshake to complete,
+    /// returns an error if the engine cannot be spawned
+    /// or does not answer the handshake
+    pub async fn try_new<T>(path: T) -> Result<std::sync::Arc<UciEngine>, EngineError>
+    where
+        T: core::fmt::Display,
+    {
+        Self::try_with_config(EngineConfig::new(path)).await
+    }
+
+    /// create new uci engine from config and wait for the uci handshake to complete,
+    /// returns an error if the engine cannot be spawned
+    /// or does not answer the handshake
+    pub async fn try_with_config(
+        config: EngineConfig,
+    ) -> Result<std::sync::Arc<UciEngine>, EngineError> {
+        let (engine, hrx) = Self::spawn(config)?;
+
+        let handshake_result = tokio::time::timeout(HANDSHAKE_TIMEOUT, hrx).await;
+
+        let err = match handshake_result {
+            Ok(Ok(Ok(()))) => return Ok(engine),
+            Ok(Ok(Err(err))) => err,
+            Ok(Err(_)) => "engine task terminated".to_string(),
+            Err(_) => "timed out waiting for uciok".to_string(),
+        };
+
+        // get rid of the unresponsive engine
+        engine.kill();
+
+        Err(EngineError::HandshakeError(err))
+    }
+
+    /// spawn engine process and start its io tasks,
+    /// also returns a receiver for the outcome of the uci handshake
+    #[allow(clippy::type_complexity)]
+    fn spawn(
+        config: EngineConfig,
+    ) -> Result<
+        (
+            std::sync::Arc<UciEngine>,
+            oneshot::Receiver<Result<(), String>>,
+        ),
+        EngineError,
+    > {
+        let path = config.path().to_string();
+
+        let transcript = match config.transcript_target() {
+            Some(target) => Some(Transcript::open(target).map_err(EngineError::TranscriptError)?),
+            _ => None,
+        };
 
         // spawn engine process
-        let mut child = Command::new(path.as_str())
-            .stdout(Stdio::piped())
-            .stdin(Stdio::piped())
-            .spawn()
-            .expect("failed to spawn engine");
+        let mut child = match config.command().spawn() {
+            Ok(child) => child,
+            Err(err) => return Err(EngineError::SpawnError(path, err)),
+        };
 
         // obtain process stdout
-        let stdout = child
-            .stdout
-            .take()
-            .expect("child did not have a handle to stdout");
+        let stdout = match child.stdout.take() {
+            Some(stdout) => stdout,
+            _ => return Err(EngineError::MissingPipeError("stdout")),
+        };
 
         // obtain process stdin
-        let stdin = child
-            .stdin
-            .take()
-            .expect("child did not have a handle to stdin");
+        let stdin = match child.stdin.take() {
+            Some(stdin) => EngineStdin {
+                stdin,
+                transcript: transcript.clone(),
+            },
+            _ => return Err(EngineError::MissingPipeError("stdin")),
+        };
 
         // stdout reader
         let reader = BufReader::new(stdout).lines();
 
+        let (stderr_tx, _) = broadcast::channel::<String>(100);
+
+        // last captured stderr lines, so that lines sent before subscribing are not lost
+        let stderr_log = std::sync::Arc::new(std::sync::Mutex::new(VecDeque::<String>::new()));
+
+        if config.stderr_mode() == StderrMode::Capture {
+            let stderr = match child.stderr.take() {
+                Some(stderr) => stderr,
+                _ => return Err(EngineError::MissingPipeError("stderr")),
+            };
+
+            let stderr_tx = stderr_tx.clone();
+            let stderr_log = stderr_log.clone();
+
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stderr).lines();
+
+                while let Ok(Some(line)) = reader.next_line().await {
+                    if log_enabled!(Level::Warn) {
+                        warn!("uci engine stderr : {}", line);
+                    }
+
+                    {
+                        let mut stderr_log = stderr_log.lock().unwrap();
+
+                        if stderr_log.len() >= STDERR_LOG_SIZE {
+                            stderr_log.pop_front();
+                        }
+
+                        stderr_log.push_back(line.clone());
+                    }
+
+                    // having no subscribers is not an error
+                    let _ = stderr_tx.send(line);
+                }
+            });
+        }
+
         // channel for receiving bestmove result
-        let (tx, rx) = mpsc::unbounded_channel::<String>();
+        let (tx, rx) = mpsc::unbounded_channel::<Result<EngineMessage, MessageParseError>>();
+
+        // engine process state, set to dead when the process exits
+        let (state_tx, state_rx) = watch::channel(EngineState::Alive);
+
+        let state_tx = std::sync::Arc::new(state_tx);
+
+        let state_tx_clone = state_tx.clone();
+
+        // channel for killing the engine process
+        let (ktx, mut krx) = mpsc::unbounded_channel::<()>();
 
         tokio::spawn(async move {
+            let state_tx = state_tx_clone;
+
             // run engine process and wait for exit code
-            let status = child
-                .wait()
-                .await
-                .expect("engine process encountered an error");
+            let wait_result = tokio::select! {
+                wait_result = child.wait() => Some(wait_result),
+                Some(()) = krx.recv() => None,
+            };
+
+            let wait_result = match wait_result {
+                Some(wait_result) => wait_result,
+                _ => {
+                    if log_enabled!(Level::Warn) {
+                        warn!("killing engine process");
+                    }
 
-            if log_enabled!(Level::Info) {
-                info!("engine process exit status : {}", status);
-            }
+                    let _ = child.start_kill();
+
+                    child.wait().await
+                }
+            };
+
+            let exit_status = match wait_result {
+                Ok(status) => {
+                    if log_enabled!(Level::Info) {
+                        info!("engine process exit status : {}", status);
+                    }
+
+                    Some(status)
+                }
+                Err(err) => {
+                    if log_enabled!(Level::Error) {
+                        error!("engine process encountered an error : {}", err);
+                    }
+
+                    None
+                }
+            };
+
+            let _ = state_tx.send(EngineState::Dead { exit_status });
         });
 
         let ai = std::sync::Arc::new(std::sync::Mutex::new(AnalysisInfo::new()));
-        let is_ready = std::sync::Arc::new(std::sync::Mutex::new(false));
+        let multipv = std::sync::Arc::new(std::sync::Mutex::new(MultiPvSnapshot::new()));
 
         let ai_clone = ai.clone();
+        let multipv_clone = multipv.clone();
 
         let (atx, _) = broadcast::channel::<AnalysisInfo>(20);
 
@@ -368,16 +1112,35 @@ impl UciEngine {
 
         let atx_clone = atx.clone();
 
+        // analysis event sender of the search in progress, if it is streamed
+        let search_info_tx = std::sync::Arc::new(std::sync::Mutex::new(
+            None::<mpsc::UnboundedSender<AnalysisEvent>>,
+        ));
+
+        // info strings of the job in progress
+        let info_strings = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+
+        let search_info_tx_clone = search_info_tx.clone();
+        let info_strings_clone = info_strings.clone();
+        let transcript_clone = transcript.clone();
+        let parser_config = config.info_parser_config();
+
         tokio::spawn(async move {
             let mut reader = reader;
             let ai = ai_clone;
             let atx = atx_clone;
+            let multipv = multipv_clone;
+            let search_info_tx = search_info_tx_clone;
+            let info_strings = info_strings_clone;
+            let transcript = transcript_clone;
 
-            let test_parse_info = env_true("TEST_PARSE_INFO");
             let mut num_lines: usize = 0;
             let mut ok_lines: usize = 0;
             let mut failed_lines: usize = 0;
 
+            // id, option and uciok lines are forwarded until uciok
+            let mut handshake_done = false;
+
             loop {
                 match reader.next_line().await {
                     Ok(line_opt) => {
@@ -388,49 +1151,89 @@ impl UciEngine {
                                 debug!("uci engine out ( {} ) : {}", num_lines, line);
                             }
 
-                            let mut is_bestmove = line.len() >= 8;
-                            let mut is_ready = line == "readyok";
+                            if let Some(transcript) = &transcript {
+                                transcript.record(Direction::FromEngine, &line);
+                            }
 
-                            if is_bestmove {
-                                is_bestmove = &line[0..8] == "bestmove";
+                            let mut ai = ai.lock().unwrap();
+
+                            let message =
+                                EngineMessage::parse_update(&line, &mut ai, &parser_config);
+
+                            // a bestmove without a valid move still ends the search
+                            let is_bestmove = matches!(
+                                message,
+                                Ok(EngineMessage::BestMove { .. })
+                                    | Err(MessageParseError::InvalidBestMoveError(_))
+                            );
+                            let is_ready = matches!(message, Ok(EngineMessage::ReadyOk));
+                            // invalid options are reported by the handshake, an engine that sends
+                            // handshake lines later must not end the job in progress
+                            let is_handshake = (!handshake_done)
+                                && matches!(
+                                    message,
+                                    Ok(EngineMessage::UciOk)
+                                        | Ok(EngineMessage::IdName(_))
+                                        | Ok(EngineMessage::IdAuthor(_))
+                                        | Ok(EngineMessage::Option(_))
+                                        | Err(MessageParseError::OptionError(_))
+                                );
+
+                            if let Ok(EngineMessage::UciOk) = message {
+                                handshake_done = true;
                             }
 
-                            {
-                                let mut ai = ai.lock().unwrap();
+                            if is_bestmove {
+                                ai.done = true;
+                            }
 
-                                let parse_result = ai.parse(&line);
+                            debug!("message {:?} , ai {:?}", message, ai);
 
-                                if is_bestmove {
-                                    ai.done = true;
-                                }
+                            if let Err(MessageParseError::InfoError(_)) = message {
+                                failed_lines += 1;
+                            } else {
+                                ok_lines += 1;
 
-                                debug!("parse result {:?} , ai {:?}", parse_result, ai);
+                                let send_result = atx.send(ai.clone());
 
-                                if parse_result.is_ok() {
-                                    ok_lines += 1;
+                                debug!("send ai result {:?}", send_result);
+                            }
 
-                                    let send_result = atx.send(*ai);
+                            drop(ai);
 
-                                    debug!("send ai result {:?}", send_result);
-                                } else {
-                                    failed_lines += 1;
+                            if let Ok(EngineMessage::Info { info, text }) = &message {
+                                // a line with only a string carries no analysis
+                                if !text.starts_with("string") {
+                                    multipv.lock().unwrap().update(info);
 
-                                    println!(
-                                        "parsing failed on {} with error {:?}",
-                                        line, parse_result
-                                    );
+                                    if let Some(info_tx) = search_info_tx.lock().unwrap().as_ref() {
+                                        let _ = info_tx.send(AnalysisEvent::Info(info.clone()));
+                                    }
                                 }
 
-                                if test_parse_info {
-                                    println!(
-                                        "read {} , parsed ok {} , failed {}",
-                                        num_lines, ok_lines, failed_lines
-                                    );
+                                if let Some(string) = &info.string {
+                                    info_strings.lock().unwrap().push(string.clone());
+
+                                    if let Some(info_tx) = search_info_tx.lock().unwrap().as_ref() {
+                                        let _ = info_tx.send(AnalysisEvent::String(string.clone()));
+                                    }
                                 }
                             }
 
-                            if is_bestmove || is_ready {
-                                let send_result = tx.send(line);
+                            if log_enabled!(Level::Debug) {
+                                debug!(
+                                    "read {} , parsed ok {} , failed {}",
+                                    num_lines, ok_lines, failed_lines
+                                );
+                            }
+
+                            if is_bestmove {
+                                // search is over, end its analysis stream
+                                search_info_tx.lock().unwrap().take();
+                            }
+
+                            if is_bestmove || is_ready || is_handshake {
+                                let send_result = tx.send(message);
 
                                 if log_enabled!(Level::Debug) {
                                     debug!("send bestmove result {:?}", send_result);
@@ -454,6 +1257,9 @@ impl UciEngine {
                 }
             }
 
+            // end the analysis stream of the search in progress, if any
+            search_info_tx.lock().unwrap().take();
+
             if log_enabled!(Level::Debug) {
                 debug!("engine read terminated");
             }
@@ -462,87 +1268,337 @@ impl UciEngine {
         // channel for sending go jobs
         let (gtx, grx) = mpsc::unbounded_channel::<GoJob>();
 
+        // channel for asking the engine to quit
+        let (qtx, qrx) = mpsc::unbounded_channel::<()>();
+
+        // channel for reporting the outcome of the handshake
+        let (htx, hrx) = oneshot::channel::<Result<(), String>>();
+
+        let id = std::sync::Arc::new(std::sync::Mutex::new(EngineId::default()));
+        let options = std::sync::Arc::new(std::sync::Mutex::new(Vec::<EngineOption>::new()));
+
         let ai_clone = ai.clone();
-        let is_ready_clone = is_ready.clone();
+        let multipv_clone = multipv.clone();
+        let id_clone = id.clone();
+        let options_clone = options.clone();
+
+        let resend_options = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // option names and values last sent to the engine, keyed by lower case name
+        let applied_options = std::sync::Arc::new(std::sync::Mutex::new(HashMap::<
+            String,
+            (String, String),
+        >::new()));
+
+        let resend_options_clone = resend_options.clone();
+        let applied_options_clone = applied_options.clone();
+        let search_info_tx_clone = search_info_tx.clone();
+        let info_strings_clone = info_strings.clone();
+        let state_rx_clone = state_rx.clone();
+        let ktx_clone = ktx.clone();
 
         tokio::spawn(async move {
             let mut stdin = stdin;
             let mut grx = grx;
+            let mut qrx = qrx;
+            let ktx = ktx_clone;
             let mut rx = rx;
             let ai = ai_clone;
-            let is_ready = is_ready_clone;
+            let multipv = multipv_clone;
+            let resend_options = resend_options_clone;
+            let search_info_tx = search_info_tx_clone;
+            let info_strings = info_strings_clone;
+            let mut state_rx = state_rx_clone;
+            let state_tx = state_tx;
+            let applied_options = applied_options_clone;
+
+            // uci handshake has to be completed before any job is processed
+            let handshake_result =
+                Self::handshake(&mut stdin, &mut rx, &id_clone, &options_clone).await;
+
+            let handshake_ok = handshake_result.is_ok();
+
+            if let Err(err) = &handshake_result {
+                if log_enabled!(Level::Error) {
+                    error!("engine handshake failed : {}", err);
+                }
+            }
+
+            let _ = htx.send(handshake_result);
+
+            if !handshake_ok {
+                return;
+            }
+
+            let mut lanes = JobLanes::default();
+
+            let exit_status = loop {
+                // sort queued jobs into lanes, so that the next job is of the highest priority
+                while let Ok(go_job) = grx.try_recv() {
+                    lanes.push(go_job);
+                }
+
+                let mut go_job = match lanes.pop() {
+                    Some(go_job) => go_job,
+                    _ => tokio::select! {
+                        go_job = grx.recv() => match go_job {
+                            Some(go_job) => go_job,
+                            // engine and all its handles are gone
+                            _ => return,
+                        },
+                        Some(()) = qrx.recv() => {
+                            let _ = write_command(&mut stdin, "quit").await;
+
+                            continue;
+                        }
+                        exit_status = engine_died(&mut state_rx) => break exit_status,
+                    },
+                };
 
-            while let Some(go_job) = grx.recv().await {
                 if log_enabled!(Level::Debug) {
                     debug!("received go job {:?}", go_job);
                 }
 
-                for command in go_job.to_commands() {
-                    let command = format!("{}\n", command);
-
+                // nobody waits for the result of an abandoned search, do not start it
+                if go_job.is_cancellable() && go_job.is_abandoned() {
                     if log_enabled!(Level::Debug) {
-                        debug!("issuing engine command : {}", command);
+                        debug!("skipping abandoned go job");
                     }
 
-                    let write_result = stdin.write_all(command.as_bytes()).await;
+                    continue;
+                }
+
+                // reject job with invalid moves or options that the engine does not declare
+                let validate_result = go_job.validate(&options_clone.lock().unwrap());
 
-                    if log_enabled!(Level::Debug) {
-                        debug!("write result {:?}", write_result);
+                if let Err(err) = validate_result {
+                    if log_enabled!(Level::Warn) {
+                        warn!("rejecting go job : {}", err);
                     }
+
+                    go_job.fail(err);
+
+                    continue;
                 }
 
-                if go_job.custom_command.is_none() && (!go_job.ponder) {
-                    {
-                        let mut ai = ai.lock().unwrap();
+                let commands = match go_job.control_command() {
+                    Some(command) => vec![command],
+                    _ => {
+                        let resend = resend_options.load(std::sync::atomic::Ordering::Relaxed);
 
-                        *ai = AnalysisInfo::new();
-                    }
+                        let mut options_changed = false;
 
-                    let recv_result = rx.recv().await.unwrap();
+                        for (key, value) in &go_job.uci_options {
+                            // a button is an action, not a state, so it is sent every time
+                            let is_button = matches!(
+                                find_option(&options_clone.lock().unwrap(), key),
+                                Some(EngineOption {
+                                    kind: OptionKind::Button,
+                                    ..
+                                })
+                            );
 
-                    if log_enabled!(Level::Debug) {
-                        debug!("recv result {:?}", recv_result);
-                    }
+                            if is_button {
+                                let _ = write_command(&mut stdin, &button_command(key)).await;
 
-                    let parts: Vec<&str> = recv_result.split(' ').collect();
+                                options_changed = true;
 
-                    let send_ai: AnalysisInfo;
+                                continue;
+                            }
 
-                    {
-                        let ai = ai.lock().unwrap();
+                            let key_lower = key.to_lowercase();
 
-                        send_ai = *ai;
-                    }
+                            let applied = applied_options
+                                .lock()
+                                .unwrap()
+                                .get(&key_lower)
+                                .map(|(_, applied_value)| applied_value)
+                                == Some(value);
 
-                    let send_is_ready: bool;
+                            if (!resend) && applied {
+                                continue;
+                            }
 
-                    {
-                        let is_ready = is_ready.lock().unwrap();
+                            let _ = write_command(&mut stdin, &setoption_command(key, value)).await;
+
+                            applied_options
+                                .lock()
+                                .unwrap()
+                                .insert(key_lower, (key.to_string(), value.to_string()));
+
+                            options_changed = true;
+                        }
+
+                        // let the engine apply the options before the search starts
+                        if options_changed && (!Self::sync_ready(&mut stdin, &mut rx).await) {
+                            if log_enabled!(Level::Error) {
+                                error!("engine output closed while applying options");
+                            }
 
-                        send_is_ready = *is_ready;
+                            let exit_status = Self::output_closed(&mut state_rx, &ktx).await;
+
+                            go_job.fail(EngineError::EngineDied { exit_status });
+
+                            break exit_status;
+                        }
+
+                        go_job.search_commands()
                     }
+                };
+
+                let awaits_result = go_job.custom_command.is_none() && (!go_job.ponder);
+
+                // reset analysis info before the engine can send infos of this job
+                if awaits_result {
+                    *ai.lock().unwrap() = AnalysisInfo::new();
+                    *multipv.lock().unwrap() = MultiPvSnapshot::new();
+                    info_strings.lock().unwrap().clear();
+                }
+
+                if let Some(info_tx) = go_job.info_tx.take() {
+                    *search_info_tx.lock().unwrap() = Some(info_tx);
+                }
+
+                let mut stop_rx = go_job.stop_rx.take();
+
+                for command in commands {
+                    let _ = write_command(&mut stdin, &command).await;
+                }
+
+                if awaits_result {
+                    let mut deadline = go_job
+                        .deadline
+                        .map(|deadline| tokio::time::Instant::now() + deadline);
+
+                    let mut stopped_at_deadline = false;
+
+                    let mut cancelled = !go_job.is_cancellable();
+
+                    let priority = go_job.priority;
+                    let mut preempted = false;
+
+                    // None if the engine did not report bestmove in time
+                    let recv_result = loop {
+                        tokio::select! {
+                            recv_result = rx.recv() => break Some(recv_result),
+                            Some(()) = qrx.recv() => {
+                                let _ = write_command(&mut stdin, "stop").await;
+                                let _ = write_command(&mut stdin, "quit").await;
+                            }
+                            _ = stop_requested(&mut stop_rx) => {
+                                stop_rx = None;
+
+                                let _ = write_command(&mut stdin, "stop").await;
+                            }
+                            _ = result_dropped(&mut go_job.rtx), if !cancelled => {
+                                if log_enabled!(Level::Debug) {
+                                    debug!("result receiver dropped, stopping search");
+                                }
+
+                                cancelled = true;
+                                stop_rx = None;
+
+                                let _ = write_command(&mut stdin, "stop").await;
+                            }
+                            Some(next_job) = grx.recv() => {
+                                let preempts = (!preempted) && next_job.preempts(priority);
+
+                                lanes.push(next_job);
+
+                                if preempts {
+                                    if log_enabled!(Level::Debug) {
+                                        debug!("preempting background search");
+                                    }
 
-                    let mut go_result = GoResult {
-                        bestmove: None,
-                        ponder: None,
-                        ai: send_ai,
-                        is_ready: recv_result == "readyok",
+                                    preempted = true;
+                                    stop_rx = None;
+
+                                    let _ = write_command(&mut stdin, "stop").await;
+                                }
+                            }
+                            _ = deadline_passed(deadline) => {
+                                if stopped_at_deadline {
+                                    break None;
+                                }
+
+                                if log_enabled!(Level::Warn) {
+                                    warn!("deadline of job passed, stopping search");
+                                }
+
+                                stopped_at_deadline = true;
+                                stop_rx = None;
+                                deadline = Some(tokio::time::Instant::now() + STOP_GRACE_PERIOD);
+
+                                let _ = write_command(&mut stdin, "stop").await;
+                            }
+                        }
                     };
 
-                    if parts.len() > 1 {
-                        go_result.bestmove = Some(parts[1].to_string());
+                    let send_ai = ai.lock().unwrap().clone();
+                    let send_multipv = multipv.lock().unwrap().clone();
+                    let send_strings = std::mem::take(&mut *info_strings.lock().unwrap());
+
+                    let recv_result = match recv_result {
+                        Some(Some(recv_result)) => recv_result,
+                        None => {
+                            if log_enabled!(Level::Error) {
+                                error!("engine did not report bestmove after stop, killing engine");
+                            }
+
+                            let _ = state_tx.send(EngineState::Unhealthy);
+                            let _ = ktx.send(());
+
+                            let mut go_result =
+                                GoResult::with_outcome(GoOutcome::Timeout, send_ai, send_multipv);
+
+                            go_result.strings = send_strings;
+
+                            let _ = go_job.rtx.unwrap().send(Ok(go_result));
+
+                            break engine_died(&mut state_rx).await;
+                        }
+                        _ => {
+                            if log_enabled!(Level::Error) {
+                                error!("engine output closed while waiting for result");
+                            }
+
+                            let exit_status = Self::output_closed(&mut state_rx, &ktx).await;
+
+                            go_job.fail(EngineError::EngineDied { exit_status });
+
+                            break exit_status;
+                        }
+                    };
+
+                    if log_enabled!(Level::Debug) {
+                        debug!("recv result {:?}", recv_result);
                     }
 
-                    if parts.len() > 3 {
-                        go_result.ponder = Some(parts[3].to_string());
+                    let mut go_result = GoResult::from_message(recv_result, send_ai, send_multipv);
+
+                    go_result.strings = send_strings;
+
+                    if preempted && go_result.outcome != GoOutcome::NoLegalMove {
+                        go_result.outcome = GoOutcome::Preempted;
                     }
 
-                    let send_result = go_job.rtx.unwrap().send(go_result);
+                    let send_result = go_job.rtx.unwrap().send(Ok(go_result));
 
                     if log_enabled!(Level::Debug) {
                         debug!("result of send go result {:?}", send_result);
                     }
                 }
+            };
+
+            // fail jobs that were queued before the engine died
+            grx.close();
+
+            while let Some(go_job) = lanes.pop() {
+                go_job.fail(EngineError::EngineDied { exit_status });
+            }
+
+            while let Some(go_job) = grx.recv().await {
+                go_job.fail(EngineError::EngineDied { exit_status });
             }
         });
 
@@ -550,52 +1606,298 @@ impl UciEngine {
             info!("spawned uci engine : {}", path);
         }
 
-        std::sync::Arc::new(UciEngine { gtx, ai, atx })
+        Ok((
+            std::sync::Arc::new(UciEngine {
+                queue: JobQueue { gtx, state_rx },
+                qtx,
+                ktx,
+                ai,
+                atx,
+                stderr_tx,
+                stderr_log,
+                transcript,
+                id,
+                options,
+                resend_options,
+                applied_options,
+            }),
+            hrx,
+        ))
+    }
+
+    /// wait for the engine process to exit after its output closed,
+    /// kill it if it does not exit in time, returns its exit status
+    async fn output_closed(
+        state_rx: &mut watch::Receiver<EngineState>,
+        ktx: &mpsc::UnboundedSender<()>,
+    ) -> Option<ExitStatus> {
+        if let Ok(exit_status) = tokio::time::timeout(EXIT_TIMEOUT, engine_died(state_rx)).await {
+            return exit_status;
+        }
+
+        let _ = ktx.send(());
+
+        engine_died(state_rx).await
+    }
+
+    /// send uci and collect id and option lines until uciok
+    async fn handshake(
+        stdin: &mut EngineStdin,
+        rx: &mut mpsc::UnboundedReceiver<Result<EngineMessage, MessageParseError>>,
+        id: &std::sync::Mutex<EngineId>,
+        options: &std::sync::Mutex<Vec<EngineOption>>,
+    ) -> Result<(), String> {
+        if let Err(err) = write_command(stdin, "uci").await {
+            return Err(format!("could not write uci : {}", err));
+        }
+
+        while let Some(message) = rx.recv().await {
+            if let Ok(EngineMessage::UciOk) = message {
+                if log_enabled!(Level::Info) {
+                    let id = id.lock().unwrap();
+
+                    info!(
+                        "engine ready : {} by {} , {} options",
+                        id.name.as_deref().unwrap_or("unknown engine"),
+                        id.author.as_deref().unwrap_or("unknown author"),
+                        options.lock().unwrap().len()
+                    );
+                }
+
+                return Ok(());
+            }
+
+            match message {
+                Ok(EngineMessage::IdName(name)) => id.lock().unwrap().name = Some(name),
+                Ok(EngineMessage::IdAuthor(author)) => id.lock().unwrap().author = Some(author),
+                Ok(EngineMessage::Option(option)) => options.lock().unwrap().push(option),
+                Err(MessageParseError::OptionError(err)) => {
+                    if log_enabled!(Level::Warn) {
+                        warn!("ignoring engine option : {}", err);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Err("engine output closed before uciok".to_string())
+    }
+
+    /// send isready and wait for readyok,
+    /// returns false if engine output closed
+    async fn sync_ready(
+        stdin: &mut EngineStdin,
+        rx: &mut mpsc::UnboundedReceiver<Result<EngineMessage, MessageParseError>>,
+    ) -> bool {
+        let _ = write_command(stdin, "isready").await;
+
+        while let Some(message) = rx.recv().await {
+            if let Ok(EngineMessage::ReadyOk) = message {
+                return true;
+            }
+
+            if log_enabled!(Level::Debug) {
+                debug!("ignoring {:?} while waiting for readyok", message);
+            }
+        }
+
+        false
+    }
+
+    /// always resend all uci options of a job, not only the changed ones,
+    /// for engines that need options to be sent again
+    pub fn set_resend_options(&self, value: bool) {
+        self.resend_options
+            .store(value, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// get engine identity
+    pub fn id(&self) -> EngineId {
+        self.id.lock().unwrap().clone()
+    }
+
+    /// get options declared by the engine
+    pub fn options(&self) -> Vec<EngineOption> {
+        self.options.lock().unwrap().clone()
+    }
+
+    /// get option declared by the engine by name ( case insensitive )
+    pub fn option<T: AsRef<str>>(&self, name: T) -> Option<EngineOption> {
+        find_option(&self.options.lock().unwrap(), name).cloned()
+    }
+
+    /// get option values sent to the engine so far, keyed by option name
+    pub fn applied_options(&self) -> HashMap<String, String> {
+        self.applied_options
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// get state of the engine process
+    pub fn state(&self) -> EngineState {
+        *self.queue.state_rx.borrow()
+    }
+
+    /// true if the engine process is running
+    pub fn is_alive(&self) -> bool {
+        self.state() == EngineState::Alive
+    }
+
+    /// subscribe to stderr lines of the engine, only sent if stderr is captured
+    pub fn subscribe_stderr(&self) -> broadcast::Receiver<String> {
+        self.stderr_tx.subscribe()
+    }
+
+    /// get last captured stderr lines of the engine, oldest first
+    pub fn stderr_log(&self) -> Vec<String> {
+        self.stderr_log.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// get protocol transcript lines kept in memory, oldest first,
+    /// empty unless the engine was configured with a ring transcript
+    pub fn transcript(&self) -> Vec<TranscriptLine> {
+        match &self.transcript {
+            Some(transcript) => transcript.lines(),
+            _ => vec![],
+        }
     }
 
     /// get analysis info
     pub fn get_ai(&self) -> AnalysisInfo {
         let ai = self.ai.lock().unwrap();
 
-        *ai
+        ai.clone()
     }
 
-    /// issue go command
-    pub fn go(&self, go_job: GoJob) -> oneshot::Receiver<GoResult> {
+    /// issue go command, the engine is kept running until the handle is dropped
+    pub fn go(self: &std::sync::Arc<Self>, go_job: GoJob) -> GoHandle {
         let mut go_job = go_job;
 
-        let (rtx, rrx): (oneshot::Sender<GoResult>, oneshot::Receiver<GoResult>) =
-            oneshot::channel();
+        let (rtx, rrx) = oneshot::channel::<Result<GoResult, EngineError>>();
 
         go_job.rtx = Some(rtx);
 
-        let send_result = self.gtx.send(go_job);
-
-        if log_enabled!(Level::Debug) {
-            debug!("send go job result {:?}", send_result);
+        if let Err(err) = self.queue.send(go_job) {
+            return GoHandle::failed(err);
         }
 
-        rrx
+        GoHandle {
+            rrx: Ok(rrx),
+            _engine: Some(self.clone()),
+        }
     }
 
-    pub fn check_ready(&self, go_job: GoJob) -> oneshot::Receiver<GoResult> {
+    /// issue go command and stream the analysis infos of the search,
+    /// the stream ends when the engine reports bestmove
+    pub fn analyze(self: &std::sync::Arc<Self>, go_job: GoJob) -> AnalysisStream {
         let mut go_job = go_job;
 
-        let (rtx, rrx): (oneshot::Sender<GoResult>, oneshot::Receiver<GoResult>) =
-            oneshot::channel();
+        let (info_tx, irx) = mpsc::unbounded_channel::<AnalysisEvent>();
 
-        go_job.rtx = Some(rtx);
+        go_job.info_tx = Some(info_tx);
 
-        let send_result = self.gtx.send(go_job);
-        if log_enabled!(Level::Debug) {
-            debug!("send go job result {:?}", send_result);
+        AnalysisStream {
+            irx,
+            go_handle: self.go(go_job),
+            snapshot: MultiPvSnapshot::new(),
+        }
+    }
+
+    /// start infinite analysis of the position of go job,
+    /// the search goes on until the session is stopped
+    pub fn start_infinite(self: &std::sync::Arc<Self>, go_job: GoJob) -> AnalysisSession {
+        let mut go_job = go_job.infinite();
+
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+        go_job.stop_rx = Some(stop_rx);
+
+        AnalysisSession {
+            engine: self.clone(),
+            stream: self.analyze(go_job),
+            stop_tx: Some(stop_tx),
+        }
+    }
+
+    /// issue job without go, resolves when the engine reports readyok
+    pub fn check_ready(self: &std::sync::Arc<Self>, go_job: GoJob) -> GoHandle {
+        self.go(go_job)
+    }
+
+    /// quit engine, stops the search in progress if any, jobs still queued fail,
+    /// waits for the engine process to exit and kills it if it does not exit in time,
+    /// returns the exit status of the engine process if it could be obtained
+    pub async fn quit(&self) -> Option<ExitStatus> {
+        let mut state_rx = self.queue.state_rx.clone();
+
+        let _ = self.qtx.send(());
+
+        if let Ok(exit_status) =
+            tokio::time::timeout(QUIT_TIMEOUT, engine_died(&mut state_rx)).await
+        {
+            return exit_status;
         }
 
-        rrx
+        if log_enabled!(Level::Warn) {
+            warn!("engine did not quit in time, killing engine process");
+        }
+
+        self.kill();
+
+        engine_died(&mut state_rx).await
     }
 
-    /// quit engine
-    pub fn quit(&self) {
-        self.go(GoJob::new().custom("quit"));
+    /// kill engine process without waiting for it to exit
+    pub fn kill(&self) {
+        let _ = self.ktx.send(());
     }
 }
+
+#[test]
+fn go_result_outcome() {
+    let go_result = |line: &str| {
+        GoResult::from_message(
+            EngineMessage::parse(line),
+            AnalysisInfo::new(),
+            MultiPvSnapshot::new(),
+        )
+    };
+    let outcome = |line: &str| go_result(line).outcome;
+
+    assert_eq!(outcome("bestmove e2e4 ponder e7e5"), GoOutcome::BestMove);
+    assert_eq!(outcome("bestmove e2e4 ponder (none)"), GoOutcome::BestMove);
+    assert_eq!(outcome("bestmove (none)"), GoOutcome::NoLegalMove);
+    assert_eq!(outcome("bestmove 0000"), GoOutcome::NoLegalMove);
+    assert_eq!(outcome("bestmove"), GoOutcome::Aborted);
+    assert_eq!(outcome("bestmove e2e9"), GoOutcome::Aborted);
+    assert_eq!(outcome("readyok"), GoOutcome::Ready);
+
+    let go_result_ponder = go_result("bestmove e2e4 ponder e7e5");
+
+    assert_eq!(go_result_ponder.bestmove, UciMove::parse("e2e4").ok());
+    assert_eq!(go_result_ponder.ponder, UciMove::parse("e7e5").ok());
+
+    let go_result_no_ponder = go_result("bestmove e2e4 ponder (none)");
+
+    assert_eq!(go_result_no_ponder.bestmove, UciMove::parse("e2e4").ok());
+    assert_eq!(go_result_no_ponder.ponder, None);
+}
+
+#[test]
+fn job_lanes_order() {
+    let mut lanes = JobLanes::default();
+
+    lanes.push(GoJob::new().custom("b1").priority(JobPriority::Background));
+    lanes.push(GoJob::new().custom("n1"));
+    lanes.push(GoJob::new().custom("r1").priority(JobPriority::Realtime));
+    lanes.push(GoJob::new().custom("n2"));
+
+    let order: Vec<String> = std::iter::from_fn(|| lanes.pop())
+        .filter_map(|go_job| go_job.custom_command)
+        .collect();
+
+    assert_eq!(order, vec!["r1", "n1", "n2", "b1"]);
+}
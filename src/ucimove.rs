@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+use thiserror::Error;
+
+/// UciMoveParseError captures possible uci move parsing errors
+#[derive(Error, Debug, Clone)]
+pub enum UciMoveParseError {
+    #[error("invalid uci move '{0}'")]
+    InvalidMoveError(String),
+}
+
+/// null move as sent by engines
+const NULL_MOVE: &str = "0000";
+
+/// board square
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Square {
+    /// file, 0 for a to 7 for h
+    pub file: u8,
+    /// rank, 0 for 1 to 7 for 8
+    pub rank: u8,
+}
+
+/// square implementation
+impl Square {
+    /// create new square, None if file or rank is off board
+    pub fn new(file: u8, rank: u8) -> Option<Self> {
+        if (file > 7) || (rank > 7) {
+            return None;
+        }
+
+        Some(Self { file, rank })
+    }
+
+    /// parse square from algebraic notation ( e.g. "e4" )
+    pub fn parse<T: AsRef<str>>(square: T) -> Option<Self> {
+        let bytes = square.as_ref().as_bytes();
+
+        if bytes.len() != 2 {
+            return None;
+        }
+
+        Self::new(bytes[0].wrapping_sub(b'a'), bytes[1].wrapping_sub(b'1'))
+    }
+}
+
+/// implement Display for Square
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file) as char, self.rank + 1)
+    }
+}
+
+/// piece kind, as used for promotions and drops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Piece {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+/// piece implementation
+impl Piece {
+    /// piece from letter ( case insensitive )
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_lowercase() {
+            'p' => Some(Piece::Pawn),
+            'n' => Some(Piece::Knight),
+            'b' => Some(Piece::Bishop),
+            'r' => Some(Piece::Rook),
+            'q' => Some(Piece::Queen),
+            'k' => Some(Piece::King),
+            _ => None,
+        }
+    }
+
+    /// lower case letter of piece
+    pub fn to_char(self) -> char {
+        match self {
+            Piece::Pawn => 'p',
+            Piece::Knight => 'n',
+            Piece::Bishop => 'b',
+            Piece::Rook => 'r',
+            Piece::Queen => 'q',
+            Piece::King => 'k',
+        }
+    }
+}
+
+/// move in long algebraic notation, as used by the uci protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UciMove {
+    /// move from square to square, with optional promotion ( e.g. "e7e8q" )
+    Normal {
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    },
+    /// piece dropped on square, used by variants like crazyhouse ( e.g. "P@e4" )
+    Drop { piece: Piece, to: Square },
+    /// null move ( "0000" )
+    Null,
+}
+
+/// uci move implementation
+impl UciMove {
+    /// parse uci move
+    pub fn parse<T: AsRef<str>>(uci: T) -> Result<Self, UciMoveParseError> {
+        let uci = uci.as_ref();
+
+        let invalid = || UciMoveParseError::InvalidMoveError(uci.to_string());
+
+        if uci == NULL_MOVE {
+            return Ok(UciMove::Null);
+        }
+
+        if !uci.is_ascii() {
+            return Err(invalid());
+        }
+
+        match uci.len() {
+            4 if &uci[1..2] == "@" => {
+                let piece = uci.chars().next().and_then(Piece::from_char);
+                let to = Square::parse(&uci[2..4]);
+
+                match (piece, to) {
+                    (Some(piece), Some(to)) => Ok(UciMove::Drop { piece, to }),
+                    _ => Err(invalid()),
+                }
+            }
+            4 | 5 => {
+                let from = Square::parse(&uci[0..2]);
+                let to = Square::parse(&uci[2..4]);
+
+                let promotion = match uci.chars().nth(4) {
+                    Some(c) => match Piece::from_char(c) {
+                        Some(piece) => Some(piece),
+                        _ => return Err(invalid()),
+                    },
+                    _ => None,
+                };
+
+                match (from, to) {
+                    (Some(from), Some(to)) => Ok(UciMove::Normal {
+                        from,
+                        to,
+                        promotion,
+                    }),
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// parse space separated list of uci moves
+    pub fn parse_list<T: AsRef<str>>(moves: T) -> Result<Vec<Self>, UciMoveParseError> {
+        moves
+            .as_ref()
+            .split_whitespace()
+            .map(UciMove::parse)
+            .collect()
+    }
+
+    /// from square, None for drops and null move
+    pub fn from(&self) -> Option<Square> {
+        match self {
+            UciMove::Normal { from, .. } => Some(*from),
+            _ => None,
+        }
+    }
+
+    /// to square, None for null move
+    pub fn to(&self) -> Option<Square> {
+        match self {
+            UciMove::Normal { to, .. } | UciMove::Drop { to, .. } => Some(*to),
+            UciMove::Null => None,
+        }
+    }
+
+    /// promotion piece, if any
+    pub fn promotion(&self) -> Option<Piece> {
+        match self {
+            UciMove::Normal { promotion, .. } => *promotion,
+            _ => None,
+        }
+    }
+
+    /// true for null move
+    pub fn is_null(&self) -> bool {
+        matches!(self, UciMove::Null)
+    }
+}
+
+/// implement Display for UciMove
+impl std::fmt::Display for UciMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UciMove::Normal {
+                from,
+                to,
+                promotion,
+            } => {
+                write!(f, "{}{}", from, to)?;
+
+                if let Some(promotion) = promotion {
+                    write!(f, "{}", promotion.to_char())?;
+                }
+
+                Ok(())
+            }
+            UciMove::Drop { piece, to } => {
+                write!(f, "{}@{}", piece.to_char().to_ascii_uppercase(), to)
+            }
+            UciMove::Null => write!(f, "{}", NULL_MOVE),
+        }
+    }
+}
+
+/// implement FromStr for UciMove
+impl std::str::FromStr for UciMove {
+    type Err = UciMoveParseError;
+
+    fn from_str(uci: &str) -> Result<Self, Self::Err> {
+        UciMove::parse(uci)
+    }
+}
+
+/// implement TryFrom<String> for UciMove
+impl std::convert::TryFrom<String> for UciMove {
+    type Error = UciMoveParseError;
+
+    fn try_from(uci: String) -> Result<Self, Self::Error> {
+        UciMove::parse(uci)
+    }
+}
+
+/// implement From<UciMove> for String
+impl std::convert::From<UciMove> for String {
+    fn from(uci_move: UciMove) -> String {
+        uci_move.to_string()
+    }
+}
+
+/// join moves to space separated string
+pub fn moves_to_string(moves: &[UciMove]) -> String {
+    moves
+        .iter()
+        .map(|uci_move| uci_move.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[test]
+fn parse_and_display() {
+    for uci in &["e2e4", "e7e8q", "a2a1n", "P@e4", "0000"] {
+        assert_eq!(UciMove::parse(uci).unwrap().to_string(), uci.to_string());
+    }
+
+    let uci_move = UciMove::parse("e7e8q").unwrap();
+
+    assert_eq!(uci_move.from(), Square::parse("e7"));
+    assert_eq!(uci_move.to(), Square::parse("e8"));
+    assert_eq!(uci_move.promotion(), Some(Piece::Queen));
+
+    assert!(UciMove::parse("0000").unwrap().is_null());
+
+    for uci in &["(none)", "e2e9", "i2i4", "e7e8x", "e2", ""] {
+        assert!(UciMove::parse(uci).is_err());
+    }
+}
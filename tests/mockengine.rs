@@ -0,0 +1,724 @@
+use tokio_stream::StreamExt;
+
+use uciengine::analysis::*;
+use uciengine::config::*;
+use uciengine::pool::*;
+use uciengine::supervisor::*;
+use uciengine::transcript::*;
+use uciengine::uciengine::*;
+use uciengine::ucimove::*;
+
+/// path of the mock engine binary
+const MOCK_ENGINE: &str = env!("CARGO_BIN_EXE_mockengine");
+
+/// mock engine config with arguments
+fn mock_config(args: &[&str]) -> EngineConfig {
+    EngineConfig::new(MOCK_ENGINE).args(args.iter())
+}
+
+/// spawn mock engine with arguments
+async fn mock_engine(args: &[&str]) -> std::sync::Arc<UciEngine> {
+    UciEngine::try_with_config(mock_config(args)).await.unwrap()
+}
+
+#[tokio::test]
+async fn handshake_and_go() {
+    let engine = mock_engine(&[]).await;
+
+    assert_eq!(engine.id().name, Some("mockengine".to_string()));
+    assert!(engine.option("multipv").is_some());
+
+    let go_result = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 5))
+        .await
+        .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::BestMove);
+    assert_eq!(go_result.bestmove, UciMove::parse("e2e4").ok());
+    assert_eq!(go_result.ai.depth, 5);
+
+    assert!(engine.quit().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn handshake_lines_after_handshake() {
+    let engine = mock_engine(&[]).await;
+
+    // the engine answers with id, option and uciok lines again
+    let _ = engine.go(GoJob::new().custom("uci")).await;
+
+    let go_result = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 2))
+        .await
+        .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::BestMove);
+    assert_eq!(go_result.bestmove, UciMove::parse("e2e4").ok());
+}
+
+#[tokio::test]
+async fn invalid_option_and_moves() {
+    let engine = mock_engine(&[]).await;
+
+    let go_result = engine
+        .go(GoJob::new().uci_opt("Hash", 100000).go_opt("depth", 1))
+        .await;
+
+    assert!(matches!(go_result, Err(EngineError::InvalidOptionError(_))));
+
+    let go_result = engine
+        .go(GoJob::new()
+            .pos_startpos()
+            .pos_moves("e2e9")
+            .go_opt("depth", 1))
+        .await;
+
+    assert!(matches!(go_result, Err(EngineError::InvalidMoveError(_))));
+}
+
+/// commands sent to the engine, as recorded in its ring transcript
+fn sent_commands(engine: &UciEngine) -> Vec<String> {
+    engine
+        .transcript()
+        .into_iter()
+        .filter(|line| line.direction == Direction::ToEngine)
+        .map(|line| line.line)
+        .collect()
+}
+
+/// number of times command was sent to the engine
+fn count_sent(engine: &UciEngine, command: &str) -> usize {
+    sent_commands(engine)
+        .iter()
+        .filter(|sent| *sent == command)
+        .count()
+}
+
+/// spawn mock engine with arguments, recording a ring transcript
+async fn mock_engine_transcript(args: &[&str]) -> std::sync::Arc<UciEngine> {
+    UciEngine::try_with_config(mock_config(args).transcript(TranscriptTarget::Ring(1000)))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn options_applied_once_buttons_always() {
+    let engine = mock_engine_transcript(&[]).await;
+
+    for _ in 0..2 {
+        engine
+            .go(GoJob::new()
+                .uci_opt("Hash", 32)
+                .uci_opt("Clear Hash", "")
+                .pos_startpos()
+                .go_opt("depth", 1))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(count_sent(&engine, "setoption name Hash value 32"), 1);
+    assert_eq!(count_sent(&engine, "setoption name Clear Hash"), 2);
+    assert_eq!(engine.applied_options().get("Clear Hash"), None);
+}
+
+#[tokio::test]
+async fn no_legal_move() {
+    let engine = mock_engine(&["--bestmove", "(none)"]).await;
+
+    let go_result = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+        .await
+        .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::NoLegalMove);
+    assert_eq!(go_result.bestmove, None);
+}
+
+#[tokio::test]
+async fn multipv_analysis() {
+    let engine = mock_engine(&[]).await;
+
+    let mut analysis = engine.analyze(
+        GoJob::new()
+            .uci_opt("MultiPV", 3)
+            .pos_startpos()
+            .go_opt("depth", 4),
+    );
+
+    let mut infos = 0;
+    let mut strings = vec![];
+
+    while let Some(event) = analysis.next().await {
+        match event {
+            AnalysisEvent::Info(_) => infos += 1,
+            AnalysisEvent::String(string) => strings.push(string),
+        }
+    }
+
+    assert_eq!(infos, 12);
+    assert_eq!(
+        strings,
+        vec![
+            "searching to depth 4".to_string(),
+            "search done".to_string()
+        ]
+    );
+    assert_eq!(analysis.snapshot().len(), 3);
+
+    let go_result = analysis.result().await.unwrap();
+
+    assert_eq!(go_result.multipv.depth, 4);
+    assert_eq!(go_result.strings, strings);
+    assert_eq!(
+        engine.applied_options().get("MultiPV"),
+        Some(&"3".to_string())
+    );
+}
+
+#[tokio::test]
+async fn info_string_before_bestmove() {
+    let engine = mock_engine(&[]).await;
+
+    let mut analysis = engine.analyze(GoJob::new().pos_startpos().go_opt("depth", 1));
+
+    let mut strings = vec![];
+
+    while let Some(event) = analysis.next().await {
+        if let AnalysisEvent::String(string) = event {
+            strings.push(string);
+        }
+    }
+
+    let go_result = analysis.result().await.unwrap();
+
+    // the string sent right before bestmove is reported once
+    assert_eq!(strings.last(), Some(&"search done".to_string()));
+    assert_eq!(strings.len(), 2);
+    assert_eq!(go_result.strings, strings);
+}
+
+#[tokio::test]
+async fn info_with_string() {
+    let path =
+        std::env::temp_dir().join(format!("uciengine_info_string_{}.txt", std::process::id()));
+
+    std::fs::write(
+        &path,
+        "0 > go depth 5\n\
+         1 < info depth 5 score cp 20 nodes 100 string search done\n\
+         2 < bestmove e2e4\n",
+    )
+    .unwrap();
+
+    let engine = mock_engine(&["--transcript", path.to_str().unwrap()]).await;
+
+    let mut analysis = engine.analyze(GoJob::new().pos_startpos().go_opt("depth", 5));
+
+    let mut events = vec![];
+
+    while let Some(event) = analysis.next().await {
+        events.push(event);
+    }
+
+    let go_result = analysis.result().await.unwrap();
+
+    let _ = std::fs::remove_file(&path);
+
+    // the fields before the string are reported as well as the string
+    assert_eq!(events.len(), 2);
+    assert!(matches!(&events[0], AnalysisEvent::Info(ai) if ai.depth == 5 && ai.nodes == 100));
+    assert!(matches!(&events[1], AnalysisEvent::String(string) if string == "search done"));
+    assert_eq!(go_result.ai.depth, 5);
+    assert_eq!(go_result.strings, vec!["search done".to_string()]);
+}
+
+#[tokio::test]
+async fn wdl_score() {
+    let engine = mock_engine(&[]).await;
+
+    let go_result = engine
+        .go(GoJob::new()
+            .uci_opt("UCI_ShowWDL", true)
+            .pos_startpos()
+            .go_opt("depth", 2))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        go_result.wdl,
+        Some(Wdl {
+            win: 340,
+            draw: 600,
+            loss: 60
+        })
+    );
+}
+
+#[tokio::test]
+async fn infinite_session() {
+    let engine = mock_engine(&[]).await;
+
+    let mut session = engine.start_infinite(GoJob::new().pos_startpos());
+
+    assert!(session.next().await.is_some());
+
+    let go_result = session
+        .retarget(GoJob::new().pos_startpos().pos_moves("e2e4"))
+        .await
+        .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::BestMove);
+
+    assert!(session.next().await.is_some());
+    assert_eq!(session.stop().await.unwrap().outcome, GoOutcome::BestMove);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn kill_on_drop() {
+    let pid_file = std::env::temp_dir().join(format!("uciengine_pid_{}.txt", std::process::id()));
+
+    let engine = mock_engine(&["--pid-file", pid_file.to_str().unwrap()]).await;
+
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+
+    let _ = std::fs::remove_file(&pid_file);
+
+    let proc_path = std::path::PathBuf::from(format!("/proc/{}", pid));
+
+    assert!(proc_path.exists());
+
+    // the search does not end by itself, so only the kill ends the process
+    let session = engine.start_infinite(GoJob::new().pos_startpos());
+
+    drop(engine);
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    assert!(proc_path.exists());
+
+    drop(session);
+
+    for _ in 0..50 {
+        if !proc_path.exists() {
+            return;
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+
+    panic!(
+        "engine process {} still running after the last handle was dropped",
+        pid
+    );
+}
+
+#[tokio::test]
+async fn session_outlives_engine_handle() {
+    let engine = mock_engine(&[]).await;
+
+    let mut session = engine.start_infinite(GoJob::new().pos_startpos());
+
+    drop(engine);
+
+    assert!(session.next().await.is_some());
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    assert_eq!(session.stop().await.unwrap().outcome, GoOutcome::BestMove);
+}
+
+#[tokio::test]
+async fn stderr_capture() {
+    let engine = UciEngine::try_with_config(mock_config(&["--stderr"]).stderr(StderrMode::Capture))
+        .await
+        .unwrap();
+
+    let mut stderr_rx = engine.subscribe_stderr();
+
+    engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+        .await
+        .unwrap();
+
+    let line = tokio::time::timeout(tokio::time::Duration::from_secs(5), stderr_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(line, "mockengine go");
+    assert_eq!(
+        engine.stderr_log(),
+        vec![
+            "mockengine started".to_string(),
+            "mockengine go".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn engine_died() {
+    let engine = mock_engine(&["--crash-on-go"]).await;
+
+    let go_result = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+        .await;
+
+    match go_result {
+        Err(EngineError::EngineDied { exit_status }) => {
+            assert_eq!(exit_status.and_then(|status| status.code()), Some(3))
+        }
+        _ => panic!("expected engine died, got {:?}", go_result),
+    }
+
+    assert!(!engine.is_alive());
+    assert!(matches!(
+        engine.go(GoJob::new().go_opt("depth", 1)).await,
+        Err(EngineError::EngineDied { .. })
+    ));
+}
+
+#[tokio::test]
+async fn supervisor_retries() {
+    let engine = SupervisedEngine::try_new(mock_config(&["--crash-on-go"]))
+        .await
+        .unwrap()
+        .max_retries(2)
+        .backoff(tokio::time::Duration::from_millis(10));
+
+    let go_result = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+        .await;
+
+    assert!(matches!(go_result, Err(EngineError::EngineDied { .. })));
+    assert_eq!(engine.restarts(), 2);
+}
+
+#[tokio::test]
+async fn deadline_timeout() {
+    let engine = mock_engine(&["--ignore-stop"]).await;
+
+    let go_result = engine
+        .go(GoJob::new()
+            .pos_startpos()
+            .infinite()
+            .deadline(tokio::time::Duration::from_millis(100)))
+        .await
+        .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::Timeout);
+    assert_eq!(go_result.ai.depth, 1);
+}
+
+#[tokio::test]
+async fn preempt_background() {
+    let engine = mock_engine(&[]).await;
+
+    let background = engine.go(GoJob::new()
+        .pos_startpos()
+        .infinite()
+        .priority(JobPriority::Background));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let realtime = engine.go(GoJob::new()
+        .pos_startpos()
+        .go_opt("depth", 1)
+        .priority(JobPriority::Realtime)
+        .preempt());
+
+    assert_eq!(background.await.unwrap().outcome, GoOutcome::Preempted);
+    assert_eq!(realtime.await.unwrap().outcome, GoOutcome::BestMove);
+}
+
+#[tokio::test]
+async fn abandoned_job_skipped() {
+    let engine = mock_engine_transcript(&["--delay", "300"]).await;
+
+    let running = engine.go(GoJob::new().pos_startpos().go_opt("depth", 1));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    // queued behind the running search and dropped before it starts
+    drop(
+        engine.go(GoJob::new()
+            .pos_startpos()
+            .pos_moves("d2d4")
+            .go_opt("depth", 1)),
+    );
+
+    let queued = engine.go(GoJob::new()
+        .pos_startpos()
+        .pos_moves("e2e4")
+        .go_opt("depth", 1));
+
+    assert_eq!(running.await.unwrap().outcome, GoOutcome::BestMove);
+    assert_eq!(queued.await.unwrap().outcome, GoOutcome::BestMove);
+
+    assert_eq!(count_sent(&engine, "position startpos moves d2d4"), 0);
+    assert_eq!(count_sent(&engine, "position startpos moves e2e4"), 1);
+}
+
+#[tokio::test]
+async fn dropped_result_stops_search() {
+    let engine = mock_engine_transcript(&[]).await;
+
+    let infinite = engine.go(GoJob::new().pos_startpos().infinite());
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    drop(infinite);
+
+    // the next job can only run once the dropped search was stopped
+    let go_result = tokio::time::timeout(
+        tokio::time::Duration::from_secs(5),
+        engine.go(GoJob::new().pos_startpos().go_opt("depth", 1)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::BestMove);
+    assert_eq!(count_sent(&engine, "stop"), 1);
+}
+
+#[tokio::test]
+async fn engine_pool() {
+    let pool = EnginePool::try_new(mock_config(&["--delay", "200"]), 3, vec![("Threads", 1)])
+        .await
+        .unwrap();
+
+    let pool = std::sync::Arc::new(pool);
+
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let pool = pool.clone();
+
+            tokio::spawn(async move {
+                pool.go(GoJob::new().pos_startpos().go_opt("depth", 1))
+                    .await
+                    .map(|go_result| go_result.outcome)
+            })
+        })
+        .collect();
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    assert_eq!(pool.idle_count(), 0);
+
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().unwrap(), GoOutcome::BestMove);
+    }
+
+    assert_eq!(pool.quit().await.len(), 3);
+}
+
+#[tokio::test]
+async fn engine_pool_queue() {
+    assert!(matches!(
+        EnginePool::try_new(mock_config(&[]), 0, Vec::<(String, String)>::new()).await,
+        Err(EngineError::EmptyPoolError)
+    ));
+
+    let pool = EnginePool::try_new(
+        mock_config(&["--delay", "300"]),
+        1,
+        Vec::<(String, String)>::new(),
+    )
+    .await
+    .unwrap();
+
+    let pool = std::sync::Arc::new(pool);
+    let pool_clone = pool.clone();
+
+    let running = tokio::spawn(async move {
+        pool_clone
+            .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+            .await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+    // wait for the busy engine, then give up
+    let waiting = tokio::time::timeout(
+        tokio::time::Duration::from_millis(50),
+        pool.go(GoJob::new().pos_startpos().go_opt("depth", 1)),
+    )
+    .await;
+
+    assert!(waiting.is_err());
+    assert_eq!(pool.queue_depth(), 0);
+    assert_eq!(running.await.unwrap().unwrap().outcome, GoOutcome::BestMove);
+}
+
+#[tokio::test]
+async fn engine_pool_dead_engine() {
+    let pool = EnginePool::try_new(mock_config(&[]), 2, Vec::<(String, String)>::new())
+        .await
+        .unwrap();
+
+    let engine = pool.engine(0).unwrap();
+
+    engine.kill();
+
+    while engine.is_alive() {
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    }
+
+    // the dead engine takes no jobs
+    for _ in 0..4 {
+        let go_result = pool
+            .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+            .await
+            .unwrap();
+
+        assert_eq!(go_result.outcome, GoOutcome::BestMove);
+    }
+
+    let pool = EnginePool::try_new(
+        mock_config(&["--crash-on-go"]),
+        2,
+        Vec::<(String, String)>::new(),
+    )
+    .await
+    .unwrap();
+
+    for _ in 0..2 {
+        assert!(matches!(
+            pool.go(GoJob::new().pos_startpos().go_opt("depth", 1))
+                .await,
+            Err(EngineError::EngineDied { .. })
+        ));
+    }
+
+    assert!(matches!(
+        pool.go(GoJob::new().pos_startpos().go_opt("depth", 1))
+            .await,
+        Err(EngineError::NoLiveEngineError)
+    ));
+}
+
+#[tokio::test]
+async fn file_transcript() {
+    let path = std::env::temp_dir().join(format!(
+        "uciengine_file_transcript_{}.txt",
+        std::process::id()
+    ));
+
+    let _ = std::fs::remove_file(&path);
+
+    let engine = UciEngine::try_with_config(
+        mock_config(&[]).transcript(TranscriptTarget::File(path.clone())),
+    )
+    .await
+    .unwrap();
+
+    let go_result = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 1))
+        .await
+        .unwrap();
+
+    assert_eq!(go_result.outcome, GoOutcome::BestMove);
+
+    // lines are written in the background
+    let mut lines = vec![];
+
+    for _ in 0..100 {
+        lines = parse_transcript(std::fs::read_to_string(&path).unwrap_or_default()).unwrap();
+
+        if lines.iter().any(|line| line.line == "bestmove e2e4") {
+            break;
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    }
+
+    assert_eq!(lines.first().map(|line| line.line.as_str()), Some("uci"));
+    assert!(lines
+        .iter()
+        .any(|line| line.direction == Direction::FromEngine && line.line == "bestmove e2e4"));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn transcript_replay() {
+    let engine = UciEngine::try_with_config(
+        mock_config(&["--bestmove", "d2d4"]).transcript(TranscriptTarget::Ring(100)),
+    )
+    .await
+    .unwrap();
+
+    let recorded = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 2))
+        .await
+        .unwrap();
+
+    let transcript: String = engine
+        .transcript()
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+
+    let path =
+        std::env::temp_dir().join(format!("uciengine_transcript_{}.txt", std::process::id()));
+
+    std::fs::write(&path, transcript).unwrap();
+
+    // the replaying engine answers with the recorded lines instead of its default move
+    let engine = mock_engine(&["--transcript", path.to_str().unwrap()]).await;
+
+    let replayed = engine
+        .go(GoJob::new().pos_startpos().go_opt("depth", 2))
+        .await
+        .unwrap();
+
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(replayed.bestmove, recorded.bestmove);
+    assert!(matches!(replayed.ai.score, Score::Cp(40)));
+}
+
+#[tokio::test]
+async fn transcript_replay_multi_option_go() {
+    let go_job = || {
+        GoJob::new()
+            .uci_opt("Hash", 64)
+            .uci_opt("Threads", 2)
+            .uci_opt("MultiPV", 1)
+            .pos_startpos()
+            .tc(Timecontrol {
+                wtime: 60000,
+                winc: 1000,
+                btime: 50000,
+                binc: 1000,
+            })
+    };
+
+    let engine = mock_engine_transcript(&["--bestmove", "g1f3"]).await;
+
+    let recorded = engine.go(go_job()).await.unwrap();
+
+    let transcript: String = engine
+        .transcript()
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+
+    let path = std::env::temp_dir().join(format!(
+        "uciengine_transcript_multi_{}.txt",
+        std::process::id()
+    ));
+
+    std::fs::write(&path, transcript).unwrap();
+
+    // the options of a new job are in the same order as in the recorded one
+    let engine = mock_engine(&["--transcript", path.to_str().unwrap()]).await;
+
+    let replayed = engine.go(go_job()).await.unwrap();
+
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(recorded.bestmove, UciMove::parse("g1f3").ok());
+    assert_eq!(replayed.bestmove, recorded.bestmove);
+}